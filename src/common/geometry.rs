@@ -0,0 +1,187 @@
+use super::{math::gcd, vector::Vector};
+
+/// A closed polygon on the integer lattice.
+///
+/// The last vertex is implicitly connected to the first one. Vertices may be either the corners
+/// of the polygon only, or every lattice point along its boundary (e.g. the tiles of a loop
+/// walked on a grid), both describe the same polygon.
+///
+/// Areas and edge lengths are computed without overflow checks, so coordinates are expected to
+/// stay well within the `i64` range (below 2^31 in absolute value).
+#[derive(Clone, Debug, PartialEq)]
+pub struct Polygon {
+    pub vertices: Vec<Vector<i64>>,
+}
+
+#[allow(dead_code)]
+impl Polygon {
+    pub fn new(vertices: Vec<Vector<i64>>) -> Self {
+        Self { vertices }
+    }
+
+    fn edges(&self) -> impl Iterator<Item = (Vector<i64>, Vector<i64>)> + '_ {
+        self.vertices
+            .iter()
+            .zip(self.vertices.iter().cycle().skip(1))
+            .map(|(a, b)| (*a, *b))
+    }
+
+    /// Twice the signed area of the polygon, computed with the shoelace formula.
+    ///
+    /// The area of a lattice polygon is a multiple of 1/2, so doubling it keeps the result exact.
+    /// It is positive when the vertices go counterclockwise in a y-up frame (clockwise on screen,
+    /// with y going down), and negative otherwise.
+    pub fn signed_double_area(&self) -> i64 {
+        self.edges().map(|(a, b)| a.x * b.y - b.x * a.y).sum()
+    }
+
+    /// Twice the unsigned area of the polygon.
+    pub fn double_area(&self) -> i64 {
+        self.signed_double_area().abs()
+    }
+
+    /// The area of the polygon.
+    pub fn area(&self) -> f64 {
+        self.double_area() as f64 / 2.0
+    }
+
+    /// Number of lattice points lying on the boundary of the polygon, vertices included.
+    pub fn boundary_points(&self) -> i64 {
+        self.edges()
+            .map(|(a, b)| gcd((b.x - a.x).abs(), (b.y - a.y).abs()))
+            .sum()
+    }
+
+    /// Number of lattice points strictly inside the polygon, using Pick's theorem:
+    /// `A = I + B / 2 - 1`.
+    pub fn interior_points(&self) -> i64 {
+        (self.double_area() - self.boundary_points() + 2) / 2
+    }
+
+    /// Dan Sunday's winding number of the polygon around `point`.
+    ///
+    /// It is 0 for points outside the polygon, and undefined for points on its boundary (see
+    /// [`Polygon::on_boundary`]).
+    pub fn winding_number(&self, point: Vector<i64>) -> i64 {
+        let mut winding_number = 0;
+        for (a, b) in self.edges() {
+            let side = is_left(a, b, point);
+            if a.y <= point.y {
+                if b.y > point.y && side > 0 {
+                    winding_number += 1;
+                }
+            } else if b.y <= point.y && side < 0 {
+                winding_number -= 1;
+            }
+        }
+        winding_number
+    }
+
+    /// Check if `point` lies on one of the polygon edges.
+    pub fn on_boundary(&self, point: Vector<i64>) -> bool {
+        self.edges().any(|(a, b)| {
+            is_left(a, b, point) == 0
+                && a.x.min(b.x) <= point.x
+                && point.x <= a.x.max(b.x)
+                && a.y.min(b.y) <= point.y
+                && point.y <= a.y.max(b.y)
+        })
+    }
+
+    /// Check if `point` is strictly inside the polygon.
+    pub fn contains(&self, point: Vector<i64>) -> bool {
+        !self.on_boundary(point) && self.winding_number(point) != 0
+    }
+}
+
+/// Test if `point` is left of (> 0), on (0) or right of (< 0) the infinite line going through
+/// `a` and `b`.
+fn is_left(a: Vector<i64>, b: Vector<i64>, point: Vector<i64>) -> i64 {
+    (b.x - a.x) * (point.y - a.y) - (point.x - a.x) * (b.y - a.y)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn polygon(vertices: &[(i64, i64)]) -> Polygon {
+        Polygon::new(vertices.iter().map(|&(x, y)| Vector::new(x, y)).collect())
+    }
+
+    #[test]
+    fn test_area() {
+        let square = polygon(&[(0, 0), (4, 0), (4, 4), (0, 4)]);
+        assert_eq!(square.signed_double_area(), 32);
+        assert_eq!(square.area(), 16.0);
+        let reversed = polygon(&[(0, 4), (4, 4), (4, 0), (0, 0)]);
+        assert_eq!(reversed.signed_double_area(), -32);
+        assert_eq!(reversed.area(), 16.0);
+        let triangle = polygon(&[(0, 0), (3, 0), (0, 1)]);
+        assert_eq!(triangle.area(), 1.5);
+    }
+
+    #[test]
+    fn test_lattice_points() {
+        let square = polygon(&[(0, 0), (4, 0), (4, 4), (0, 4)]);
+        assert_eq!(square.boundary_points(), 16);
+        assert_eq!(square.interior_points(), 9);
+        let triangle = polygon(&[(0, 0), (4, 0), (0, 2)]);
+        assert_eq!(triangle.boundary_points(), 8);
+        assert_eq!(triangle.interior_points(), 1);
+    }
+
+    #[test]
+    fn test_lattice_points_with_every_boundary_vertex() {
+        let corners = polygon(&[(0, 0), (2, 0), (2, 2), (0, 2)]);
+        let walked = polygon(&[
+            (0, 0),
+            (1, 0),
+            (2, 0),
+            (2, 1),
+            (2, 2),
+            (1, 2),
+            (0, 2),
+            (0, 1),
+        ]);
+        assert_eq!(walked.double_area(), corners.double_area());
+        assert_eq!(walked.boundary_points(), corners.boundary_points());
+        assert_eq!(walked.interior_points(), 1);
+    }
+
+    #[test]
+    fn test_contains() {
+        // U shape
+        let shape = polygon(&[
+            (0, 0),
+            (5, 0),
+            (5, 5),
+            (3, 5),
+            (3, 2),
+            (2, 2),
+            (2, 5),
+            (0, 5),
+        ]);
+        assert!(shape.contains(Vector::new(1, 1)));
+        assert!(shape.contains(Vector::new(4, 4)));
+        assert!(!shape.contains(Vector::new(2, 4)));
+        assert!(!shape.contains(Vector::new(6, 1)));
+        assert!(!shape.contains(Vector::new(0, 3)));
+        assert!(shape.on_boundary(Vector::new(0, 3)));
+        assert!(shape.on_boundary(Vector::new(3, 3)));
+        assert!(!shape.on_boundary(Vector::new(1, 1)));
+        let count = (0..=5)
+            .flat_map(|y| (0..=5).map(move |x| Vector::new(x, y)))
+            .filter(|point| shape.contains(*point))
+            .count();
+        assert_eq!(count as i64, shape.interior_points());
+    }
+
+    #[test]
+    fn test_winding_number_orientation() {
+        let square = polygon(&[(0, 0), (4, 0), (4, 4), (0, 4)]);
+        let reversed = polygon(&[(0, 4), (4, 4), (4, 0), (0, 0)]);
+        assert_eq!(square.winding_number(Vector::new(2, 2)), 1);
+        assert_eq!(reversed.winding_number(Vector::new(2, 2)), -1);
+        assert_eq!(square.winding_number(Vector::new(5, 2)), 0);
+    }
+}
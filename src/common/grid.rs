@@ -1,5 +1,5 @@
+use std::fmt::Display;
 use std::ops::Add;
-use std::{fmt::Display, usize};
 
 use itertools::Itertools;
 
@@ -38,15 +38,7 @@ impl Coordinate for GridCoordinates {
 }
 
 #[allow(dead_code)]
-impl<T: Display + Clone + Default> Grid<T> {
-    pub fn new(width: usize, height: usize) -> Self {
-        Self {
-            data: vec![T::default(); width * height],
-            width,
-            height,
-        }
-    }
-
+impl<T> Grid<T> {
     pub fn from_data(data: Vec<T>, width: usize, height: usize) -> Self {
         assert_eq!(data.len(), width * height);
         Self {
@@ -71,23 +63,45 @@ impl<T: Display + Clone + Default> Grid<T> {
         }
     }
 
-    pub fn iter_rows(&self) -> NestedIterator<&T> {
-        Box::new(self.into_iter())
+    /// The coordinates of the cells around `at` that are inside the grid, diagonals included.
+    pub fn neighbors<C: Coordinate>(&self, at: C) -> impl Iterator<Item = C> + '_ {
+        let (x, y) = (at.x() as i64, at.y() as i64);
+        (-1..=1)
+            .flat_map(|dy| (-1..=1).map(move |dx| (dx, dy)))
+            .filter(|delta| *delta != (0, 0))
+            .map(move |(dx, dy)| (x + dx, y + dy))
+            .filter(|(x, y)| {
+                (0..self.width as i64).contains(x) && (0..self.height as i64).contains(y)
+            })
+            .map(|(x, y)| C::new(x as usize, y as usize))
     }
 
-    pub fn iter_columns(&self) -> NestedIterator<&T> {
-        Box::new(ColumnIterator { array: self, x: 0 })
-    }
-
-    pub fn iter_coords<C: Coordinate>(&self) -> impl Iterator<Item = C> + DoubleEndedIterator + '_ {
+    pub fn iter_coords<C: Coordinate>(&self) -> impl DoubleEndedIterator<Item = C> + '_ {
         (0..self.height).flat_map(move |y| (0..self.width).map(move |x| C::new(x, y)))
     }
 
-    pub fn iter_col_coords<C: Coordinate>(
-        &self,
-    ) -> impl Iterator<Item = C> + DoubleEndedIterator + '_ {
+    pub fn iter_col_coords<C: Coordinate>(&self) -> impl DoubleEndedIterator<Item = C> + '_ {
         (0..self.width).flat_map(move |x| (0..self.height).map(move |y| C::new(x, y)))
     }
+}
+
+#[allow(dead_code)]
+impl<T: Display + Clone + Default> Grid<T> {
+    pub fn new(width: usize, height: usize) -> Self {
+        Self {
+            data: vec![T::default(); width * height],
+            width,
+            height,
+        }
+    }
+
+    pub fn iter_rows(&self) -> NestedIterator<'_, &T> {
+        Box::new(self.into_iter())
+    }
+
+    pub fn iter_columns(&self) -> NestedIterator<'_, &T> {
+        Box::new(ColumnIterator { array: self, x: 0 })
+    }
 
     pub fn print(&self) {
         println!("{}", self.format());
@@ -203,10 +217,9 @@ impl<T> FromIterator<Vec<T>> for Grid<T> {
         let mut width = None;
         let mut height = 0;
         for row in iter {
-            if width.is_none() {
-                width = Some(row.len());
-            } else {
-                assert_eq!(width.unwrap(), row.len(), "rows must have the same length");
+            match width {
+                None => width = Some(row.len()),
+                Some(width) => assert_eq!(width, row.len(), "rows must have the same length"),
             }
             data.extend(row);
             height += 1;
@@ -283,6 +296,16 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_neighbors() {
+        let array = Grid::from_iter(vec![vec![1, 2, 3], vec![4, 5, 6]]);
+        assert_eq!(
+            array.neighbors((0, 0)).collect::<Vec<GridCoordinates>>(),
+            vec![(1, 0), (0, 1), (1, 1)]
+        );
+        assert_eq!(array.neighbors((1, 1)).count(), 5);
+    }
+
     #[test]
     fn test_iter_col_coords() {
         let array = Grid::from_iter(vec![vec![1, 2, 3], vec![4, 5, 6]]);
@@ -0,0 +1,234 @@
+#![allow(clippy::single_range_in_vec_init)]
+
+use std::ops::Range;
+
+use num_traits::PrimInt;
+
+/// A set of integers stored as a list of half-open ranges.
+///
+/// Ranges are kept normalized: sorted, non-empty, and neither overlapping nor adjacent, so two
+/// sets containing the same values are always equal.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct IntervalSet<T = u64> {
+    ranges: Vec<Range<T>>,
+}
+
+#[allow(dead_code)]
+impl<T: PrimInt> IntervalSet<T> {
+    pub fn new() -> Self {
+        Self { ranges: Vec::new() }
+    }
+
+    pub fn from_ranges<I: IntoIterator<Item = Range<T>>>(ranges: I) -> Self {
+        let mut ranges = ranges
+            .into_iter()
+            .filter(|range| range.start < range.end)
+            .collect::<Vec<_>>();
+        ranges.sort_by_key(|range| range.start);
+        let mut merged: Vec<Range<T>> = Vec::with_capacity(ranges.len());
+        for range in ranges {
+            match merged.last_mut() {
+                Some(last) if range.start <= last.end => last.end = last.end.max(range.end),
+                _ => merged.push(range),
+            }
+        }
+        Self { ranges: merged }
+    }
+
+    pub fn ranges(&self) -> &[Range<T>] {
+        &self.ranges
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Range<T>> {
+        self.ranges.iter()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ranges.is_empty()
+    }
+
+    /// Number of values in the set.
+    pub fn len(&self) -> T {
+        self.ranges
+            .iter()
+            .fold(T::zero(), |total, range| total + (range.end - range.start))
+    }
+
+    pub fn min(&self) -> Option<T> {
+        self.ranges.first().map(|range| range.start)
+    }
+
+    pub fn max(&self) -> Option<T> {
+        self.ranges.last().map(|range| range.end - T::one())
+    }
+
+    pub fn contains(&self, value: T) -> bool {
+        let index = self.ranges.partition_point(|range| range.end <= value);
+        self.ranges
+            .get(index)
+            .is_some_and(|range| range.contains(&value))
+    }
+
+    pub fn insert(&mut self, range: Range<T>) {
+        let ranges = std::mem::take(&mut self.ranges);
+        *self = Self::from_ranges(ranges.into_iter().chain([range]));
+    }
+
+    pub fn union(&self, other: &Self) -> Self {
+        Self::from_ranges(self.ranges.iter().chain(&other.ranges).cloned())
+    }
+
+    pub fn intersection(&self, other: &Self) -> Self {
+        let mut ranges = Vec::new();
+        let (mut left, mut right) = (0, 0);
+        while left < self.ranges.len() && right < other.ranges.len() {
+            let a = &self.ranges[left];
+            let b = &other.ranges[right];
+            let start = a.start.max(b.start);
+            let end = a.end.min(b.end);
+            if start < end {
+                ranges.push(start..end);
+            }
+            if a.end < b.end {
+                left += 1;
+            } else {
+                right += 1;
+            }
+        }
+        Self { ranges }
+    }
+
+    pub fn difference(&self, other: &Self) -> Self {
+        let mut ranges = Vec::new();
+        let mut right = 0;
+        for range in &self.ranges {
+            let mut start = range.start;
+            while right < other.ranges.len() && other.ranges[right].end <= start {
+                right += 1;
+            }
+            let mut cut = right;
+            while start < range.end && cut < other.ranges.len() {
+                let hole = &other.ranges[cut];
+                if hole.start >= range.end {
+                    break;
+                }
+                if hole.start > start {
+                    ranges.push(start..hole.start);
+                }
+                start = start.max(hole.end);
+                cut += 1;
+            }
+            if start < range.end {
+                ranges.push(start..range.end);
+            }
+        }
+        Self { ranges }
+    }
+
+    /// Apply a piecewise-linear function to the set.
+    ///
+    /// Each mapping is a `(source, destination)` pair, moving the values of `source` so that
+    /// `source.start` lands on `destination`. Values not covered by any mapping are kept as-is.
+    /// If mappings overlap, the first one covering a value wins.
+    pub fn remap<I: IntoIterator<Item = (Range<T>, T)>>(&self, mappings: I) -> Self {
+        let mut remaining = self.clone();
+        let mut mapped = Vec::new();
+        for (source, destination) in mappings {
+            let source = Self::from_ranges([source]);
+            let Some(source_start) = source.min() else {
+                continue;
+            };
+            for range in remaining.intersection(&source).iter() {
+                mapped.push(
+                    destination + (range.start - source_start)
+                        ..destination + (range.end - source_start),
+                );
+            }
+            remaining = remaining.difference(&source);
+        }
+        Self::from_ranges(mapped.into_iter().chain(remaining.ranges))
+    }
+}
+
+impl<T: PrimInt> FromIterator<Range<T>> for IntervalSet<T> {
+    fn from_iter<I: IntoIterator<Item = Range<T>>>(iter: I) -> Self {
+        Self::from_ranges(iter)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn set(ranges: &[Range<u64>]) -> IntervalSet {
+        IntervalSet::from_ranges(ranges.iter().cloned())
+    }
+
+    #[test]
+    fn test_normalize() {
+        assert_eq!(
+            set(&[5..10, 0..3, 3..4, 8..12, 7..7]).ranges(),
+            &[0..4, 5..12]
+        );
+        assert!(set(&[3..3]).is_empty());
+    }
+
+    #[test]
+    fn test_queries() {
+        let intervals = set(&[0..4, 10..12]);
+        assert_eq!(intervals.len(), 6);
+        assert_eq!(intervals.min(), Some(0));
+        assert_eq!(intervals.max(), Some(11));
+        assert!(intervals.contains(3));
+        assert!(!intervals.contains(4));
+        assert!(intervals.contains(10));
+        assert!(!intervals.contains(12));
+    }
+
+    #[test]
+    fn test_insert() {
+        let mut intervals = set(&[0..4, 10..12]);
+        intervals.insert(4..10);
+        assert_eq!(intervals.ranges(), &[0..12]);
+    }
+
+    #[test]
+    fn test_union() {
+        assert_eq!(set(&[0..4]).union(&set(&[4..6, 8..9])), set(&[0..6, 8..9]));
+    }
+
+    #[test]
+    fn test_intersection() {
+        assert_eq!(
+            set(&[0..5, 10..20]).intersection(&set(&[3..12, 15..16, 19..30])),
+            set(&[3..5, 10..12, 15..16, 19..20])
+        );
+        assert!(set(&[0..5]).intersection(&set(&[5..10])).is_empty());
+    }
+
+    #[test]
+    fn test_difference() {
+        assert_eq!(
+            set(&[0..10, 20..30]).difference(&set(&[2..4, 6..22, 25..26])),
+            set(&[0..2, 4..6, 22..25, 26..30])
+        );
+        assert!(set(&[2..4]).difference(&set(&[0..10])).is_empty());
+        assert_eq!(set(&[2..4]).difference(&set(&[])), set(&[2..4]));
+    }
+
+    #[test]
+    fn test_remap() {
+        let intervals = set(&[0..20]);
+        assert_eq!(
+            intervals.remap([(5..10, 100), (15..30, 50)]),
+            set(&[0..5, 10..15, 50..55, 100..105])
+        );
+        // First mapping wins on overlaps
+        assert_eq!(
+            intervals.remap([(0..10, 100), (5..20, 200)]),
+            set(&[100..110, 205..215])
+        );
+        // Mapped values merge with unmapped ones
+        assert_eq!(set(&[0..10]).remap([(0..5, 10)]), set(&[5..15]));
+    }
+}
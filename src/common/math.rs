@@ -0,0 +1,256 @@
+use num_traits::{Euclid, PrimInt, Signed};
+
+/// Greatest common divisor of `a` and `b`, always non-negative.
+///
+/// Panics if the result overflows `T`, which only happens for signed types when it is
+/// `-T::min_value()`, see [`checked_gcd`] for a non-panicking version.
+pub fn gcd<T: PrimInt>(a: T, b: T) -> T {
+    checked_gcd(a, b).expect("gcd overflow")
+}
+
+/// Greatest common divisor of `a` and `b`, or `None` if it overflows `T`.
+pub fn checked_gcd<T: PrimInt>(a: T, b: T) -> Option<T> {
+    let mut a = a;
+    let mut b = b;
+    while b != T::zero() {
+        // The remainder of `T::min_value()` divided by -1 overflows, but it is always 0
+        let remainder = if b.checked_add(&T::one()) == Some(T::zero()) {
+            T::zero()
+        } else {
+            a % b
+        };
+        (a, b) = (b, remainder);
+    }
+    if a < T::zero() {
+        T::zero().checked_sub(&a)
+    } else {
+        Some(a)
+    }
+}
+
+/// Least common multiple of `a` and `b`.
+///
+/// Panics if the result overflows `T`, see [`checked_lcm`] for a non-panicking version.
+#[allow(dead_code)]
+pub fn lcm<T: PrimInt>(a: T, b: T) -> T {
+    checked_lcm(a, b).expect("lcm overflow")
+}
+
+/// Least common multiple of `a` and `b`, or `None` if it overflows `T`.
+///
+/// Divides before multiplying, so it only overflows when the result itself does not fit.
+pub fn checked_lcm<T: PrimInt>(a: T, b: T) -> Option<T> {
+    if a == T::zero() || b == T::zero() {
+        return Some(T::zero());
+    }
+    let lcm = (a / checked_gcd(a, b)?).checked_mul(&b)?;
+    if lcm < T::zero() {
+        T::zero().checked_sub(&lcm)
+    } else {
+        Some(lcm)
+    }
+}
+
+/// Extended Euclidean algorithm: returns `(g, x, y)` such that `a * x + b * y == g`, where `g`
+/// is the greatest common divisor of `a` and `b`.
+#[allow(dead_code)]
+pub fn extended_gcd<T: PrimInt + Signed>(a: T, b: T) -> (T, T, T) {
+    let (mut old_r, mut r) = (a, b);
+    let (mut old_x, mut x) = (T::one(), T::zero());
+    let (mut old_y, mut y) = (T::zero(), T::one());
+    while r != T::zero() {
+        let quotient = old_r / r;
+        (old_r, r) = (r, old_r - quotient * r);
+        (old_x, x) = (x, old_x - quotient * x);
+        (old_y, y) = (y, old_y - quotient * y);
+    }
+    if old_r < T::zero() {
+        (-old_r, -old_x, -old_y)
+    } else {
+        (old_r, old_x, old_y)
+    }
+}
+
+/// Inverse of `a` modulo `modulus`, or `None` if they are not coprime or `modulus` is not
+/// positive.
+#[allow(dead_code)]
+pub fn mod_inverse<T: PrimInt + Signed + Euclid>(a: T, modulus: T) -> Option<T> {
+    if modulus <= T::zero() {
+        return None;
+    }
+    let (g, x, _) = extended_gcd(a.rem_euclid(&modulus), modulus);
+    if g == T::one() {
+        Some(x.rem_euclid(&modulus))
+    } else {
+        None
+    }
+}
+
+/// Add `a` and `b` modulo `modulus`, without overflowing. `a` and `b` must be in `0..modulus`.
+fn add_mod<T: PrimInt>(a: T, b: T, modulus: T) -> T {
+    if a >= modulus - b {
+        a - (modulus - b)
+    } else {
+        a + b
+    }
+}
+
+/// Multiply `a` and `b` modulo `modulus`, without overflowing when the product does not fit in
+/// `T`. `a` and `b` must be in `0..modulus`.
+fn mul_mod<T: PrimInt>(a: T, b: T, modulus: T) -> T {
+    if let Some(product) = a.checked_mul(&b) {
+        return product % modulus;
+    }
+    let two = T::one() + T::one();
+    let mut result = T::zero();
+    let mut a = a;
+    let mut b = b;
+    while b > T::zero() {
+        if b % two == T::one() {
+            result = add_mod(result, a, modulus);
+        }
+        a = add_mod(a, a, modulus);
+        b = b / two;
+    }
+    result
+}
+
+/// Solve a system of congruences `x ≡ residue (mod modulus)` with the Chinese Remainder Theorem.
+///
+/// Moduli don't need to be pairwise coprime. Returns `(x, m)` where `x` is the smallest
+/// non-negative solution and `m` the least common multiple of the moduli, so that all the
+/// solutions are `x + k * m`. Returns `None` if the system has no solution, if a modulus is
+/// not positive, or if `m` overflows `T`.
+pub fn chinese_remainder<T: PrimInt + Signed + Euclid>(congruences: &[(T, T)]) -> Option<(T, T)> {
+    let mut solution = T::zero();
+    let mut modulus = T::one();
+    for &(residue, other_modulus) in congruences {
+        if other_modulus <= T::zero() {
+            return None;
+        }
+        let residue = residue.rem_euclid(&other_modulus);
+        let g = gcd(modulus, other_modulus);
+        let difference = residue - solution;
+        if difference % g != T::zero() {
+            return None;
+        }
+        let new_modulus = (modulus / g).checked_mul(&other_modulus)?;
+        // Solve solution + modulus * k ≡ residue (mod other_modulus) for k
+        let reduced_modulus = other_modulus / g;
+        let inverse = mod_inverse(modulus / g, reduced_modulus)?;
+        let k = mul_mod(
+            (difference / g).rem_euclid(&reduced_modulus),
+            inverse,
+            reduced_modulus,
+        );
+        // k < reduced_modulus, so modulus * k < new_modulus, and solution < modulus
+        solution = add_mod(solution, modulus * k, new_modulus);
+        modulus = new_modulus;
+    }
+    Some((solution, modulus))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_gcd() {
+        assert_eq!(gcd(12, 18), 6);
+        assert_eq!(gcd(18u64, 12), 6);
+        assert_eq!(gcd(7, 0), 7);
+        assert_eq!(gcd(0, 7), 7);
+        assert_eq!(gcd(-12i64, 18), 6);
+        assert_eq!(gcd(i64::MIN, 6), 2);
+        assert_eq!(gcd(i64::MIN, -1), 1);
+        assert_eq!(checked_gcd(i64::MIN, 0), None);
+        assert_eq!(checked_gcd(i64::MIN, i64::MIN), None);
+        assert_eq!(checked_lcm(i64::MIN, i64::MIN), None);
+    }
+
+    #[test]
+    fn test_lcm() {
+        assert_eq!(lcm(4, 6), 12);
+        assert_eq!(lcm(0u32, 6), 0);
+        assert_eq!(lcm(-4i32, 6), 12);
+        // a * b would overflow, but the result fits
+        let big = u64::MAX / 2;
+        assert_eq!(lcm(big, big), big);
+        assert_eq!(checked_lcm(u64::MAX, u64::MAX - 1), None);
+    }
+
+    #[test]
+    fn test_extended_gcd() {
+        for (a, b) in [(240i64, 46), (46, 240), (17, 5), (-12, 18), (0, 5)] {
+            let (g, x, y) = extended_gcd(a, b);
+            assert_eq!(g, gcd(a, b));
+            assert_eq!(a * x + b * y, g);
+        }
+    }
+
+    #[test]
+    fn test_mod_inverse() {
+        assert_eq!(mod_inverse(3i64, 11), Some(4));
+        assert_eq!(mod_inverse(-3i64, 11), Some(7));
+        assert_eq!(mod_inverse(6i64, 9), None);
+        assert_eq!(mod_inverse(3i64, 0), None);
+        assert_eq!(mod_inverse(3i64, -11), None);
+    }
+
+    #[test]
+    fn test_chinese_remainder() {
+        assert_eq!(
+            chinese_remainder(&[(2i64, 3), (3, 5), (2, 7)]),
+            Some((23, 105))
+        );
+        // Non-coprime moduli
+        assert_eq!(chinese_remainder(&[(2i64, 6), (8, 10)]), Some((8, 30)));
+        assert_eq!(chinese_remainder(&[(1i64, 6), (2, 10)]), None);
+        // Zero offsets degenerate to the least common multiple
+        assert_eq!(chinese_remainder(&[(0i64, 4), (0, 6)]), Some((0, 12)));
+        assert_eq!(chinese_remainder::<i64>(&[]), Some((0, 1)));
+    }
+
+    #[test]
+    fn test_mul_mod() {
+        // Moduli above half of the maximum, where doubling overflows
+        let modulus = i64::MAX;
+        assert_eq!(mul_mod(modulus - 1, modulus - 1, modulus), 1);
+        assert_eq!(mul_mod(modulus - 1, 2, modulus), modulus - 2);
+        let modulus = i64::MAX / 4 * 3;
+        assert_eq!(mul_mod(modulus - 2, modulus - 3, modulus), 6);
+    }
+
+    #[test]
+    fn test_chinese_remainder_overflow() {
+        let modulus = 5_000_000_000_000_000_002i64;
+        assert!(modulus > i64::MAX / 2);
+        assert_eq!(
+            chinese_remainder(&[(1i64, 3), (modulus - 7, modulus)]),
+            None
+        );
+        assert_eq!(
+            chinese_remainder(&[(1i64, 2), (modulus - 7, modulus)]),
+            Some((modulus - 7, modulus))
+        );
+        assert_eq!(
+            chinese_remainder(&[(modulus - 7, modulus), (1i64, 2)]),
+            Some((modulus - 7, modulus))
+        );
+        assert_eq!(chinese_remainder(&[(1i64, 0)]), None);
+        assert_eq!(chinese_remainder(&[(1i64, -3)]), None);
+    }
+
+    #[test]
+    fn test_chinese_remainder_large_moduli() {
+        let moduli = [79i64, 73, 71, 61, 47, 59].map(|prime| prime * 263);
+        let x = 1_234_567_890_123i64;
+        let congruences = moduli
+            .iter()
+            .map(|modulus| (x % modulus, *modulus))
+            .collect::<Vec<_>>();
+        let (solution, modulus) = chinese_remainder(&congruences).unwrap();
+        assert_eq!(modulus, moduli.iter().copied().reduce(lcm).unwrap());
+        assert_eq!(solution, x % modulus);
+    }
+}
@@ -0,0 +1,75 @@
+use std::{collections::HashMap, hash::Hash};
+
+/// The function memoized by a [`Memo`]. It receives the memo itself to make recursive calls.
+pub type MemoFunction<'a, K, V> = dyn Fn(&mut Memo<'a, K, V>, K) -> V + 'a;
+
+/// A memoized recursive function, with a cache local to this value.
+///
+/// Unlike `#[memoize]`, keys don't have to own the function inputs: the function can borrow
+/// its inputs from the enclosing scope and use cheap keys, like offsets into them. The cache is
+/// dropped with the memo, so it never leaks between unrelated calls.
+pub struct Memo<'a, K, V> {
+    cache: HashMap<K, V>,
+    function: &'a MemoFunction<'a, K, V>,
+}
+
+impl<'a, K: Hash + Eq + Clone, V: Clone> Memo<'a, K, V> {
+    pub fn new(function: &'a MemoFunction<'a, K, V>) -> Self {
+        Self {
+            cache: HashMap::new(),
+            function,
+        }
+    }
+
+    /// Get the function value for `key`, computing it if it's not cached yet.
+    pub fn get(&mut self, key: K) -> V {
+        if let Some(value) = self.cache.get(&key) {
+            return value.clone();
+        }
+        let function = self.function;
+        let value = function(self, key.clone());
+        self.cache.insert(key, value.clone());
+        value
+    }
+
+    /// Number of cached values.
+    #[allow(dead_code)]
+    pub fn len(&self) -> usize {
+        self.cache.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_recursion() {
+        let fibonacci = |memo: &mut Memo<u64, u64>, n: u64| {
+            if n < 2 {
+                n
+            } else {
+                memo.get(n - 1) + memo.get(n - 2)
+            }
+        };
+        let mut memo = Memo::new(&fibonacci);
+        assert_eq!(memo.get(90), 2880067194370816120);
+        assert_eq!(memo.len(), 91);
+    }
+
+    #[test]
+    fn test_borrowed_inputs() {
+        // Number of ways to reach the end of `steps`, moving by 1 or 2
+        let steps = [true, true, false, true, true, true];
+        let ways = |memo: &mut Memo<usize, usize>, index: usize| {
+            if index == steps.len() - 1 {
+                1
+            } else if index >= steps.len() || !steps[index] {
+                0
+            } else {
+                memo.get(index + 1) + memo.get(index + 2)
+            }
+        };
+        assert_eq!(Memo::new(&ways).get(0), 2);
+    }
+}
@@ -1,2 +1,6 @@
+pub mod geometry;
 pub mod grid;
+pub mod interval;
+pub mod math;
+pub mod memo;
 pub mod vector;
@@ -1,7 +1,4 @@
-use std::{
-    ops::{Add, AddAssign, Div, Neg, Sub},
-    usize,
-};
+use std::ops::{Add, AddAssign, Div, Neg, Sub};
 
 use num_traits::Float;
 
@@ -100,6 +97,7 @@ impl<T: Sub<Output = T>> Sub for Vector<T> {
     }
 }
 
+#[allow(dead_code)]
 pub struct Scalar<T>(pub T);
 
 impl<T: Float> Div<Vector<T>> for Scalar<T> {
@@ -124,6 +122,7 @@ impl<T: Neg<Output = T>> Neg for Vector<T> {
     }
 }
 
+#[allow(dead_code)]
 pub trait ConvertTo<T> {
     fn convert(self) -> T;
 }
@@ -1,100 +1,339 @@
 use aoc_2023_rust_flupke::Problem;
-use std::collections::HashMap;
+use std::collections::{HashMap, VecDeque};
 
-pub struct Day1;
+pub struct Day1 {
+    vocabulary: Vocabulary,
+}
+
+impl Day1 {
+    /// Create the day 1 solver, with the number words described by `spec` (see
+    /// [`Vocabulary::from_spec`]), or english words if `None`.
+    pub fn new(spec: Option<&str>) -> Result<Self, String> {
+        let vocabulary = match spec {
+            Some(spec) => Vocabulary::from_spec(spec)?,
+            None => Vocabulary::builtin(Language::English, false),
+        };
+        Ok(Self { vocabulary })
+    }
+}
+
+const DIGITS: [(&str, char); 10] = [
+    ("0", '0'),
+    ("1", '1'),
+    ("2", '2'),
+    ("3", '3'),
+    ("4", '4'),
+    ("5", '5'),
+    ("6", '6'),
+    ("7", '7'),
+    ("8", '8'),
+    ("9", '9'),
+];
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Language {
+    English,
+    French,
+    German,
+    Spanish,
+}
+
+impl Language {
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "english" => Some(Self::English),
+            "french" => Some(Self::French),
+            "german" => Some(Self::German),
+            "spanish" => Some(Self::Spanish),
+            _ => None,
+        }
+    }
+
+    /// The number words of the language, indexed by digit.
+    fn words(&self) -> [&'static str; 10] {
+        match self {
+            Self::English => [
+                "zero", "one", "two", "three", "four", "five", "six", "seven", "eight", "nine",
+            ],
+            Self::French => [
+                "zéro", "un", "deux", "trois", "quatre", "cinq", "six", "sept", "huit", "neuf",
+            ],
+            Self::German => [
+                "null", "eins", "zwei", "drei", "vier", "fünf", "sechs", "sieben", "acht", "neun",
+            ],
+            Self::Spanish => [
+                "cero", "uno", "dos", "tres", "cuatro", "cinco", "seis", "siete", "ocho", "nueve",
+            ],
+        }
+    }
+}
+
+/// The number words recognized in calibration lines, on top of the digits themselves.
+///
+/// Digits 1 to 9 are always recognized, 0 only when the vocabulary has a word for it.
+#[derive(Clone, Debug, Default, PartialEq)]
+struct Vocabulary {
+    words: Vec<(String, char)>,
+}
+
+impl Vocabulary {
+    fn builtin(language: Language, with_zero: bool) -> Self {
+        let first_digit = if with_zero { 0 } else { 1 };
+        let words = language
+            .words()
+            .iter()
+            .enumerate()
+            .skip(first_digit)
+            .map(|(digit, word)| (word.to_string(), (b'0' + digit as u8) as char))
+            .collect();
+        Self { words }
+    }
+
+    /// Parse a vocabulary from `word=digit` lines. Blank lines and lines starting with `#` are
+    /// ignored.
+    fn parse(text: &str) -> Result<Self, String> {
+        let words = text
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .map(|line| {
+                let (word, digit) = line
+                    .split_once('=')
+                    .ok_or(format!("invalid vocabulary line: {:?}", line))?;
+                let (word, digit) = (word.trim(), digit.trim());
+                match (word.is_empty(), digit.as_bytes()) {
+                    (false, &[digit]) if digit.is_ascii_digit() => {
+                        Ok((word.to_string(), digit as char))
+                    }
+                    _ => Err(format!("invalid vocabulary line: {:?}", line)),
+                }
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+        Ok(Self { words })
+    }
+
+    /// Load a vocabulary from a language name (`english`, `french`, `german` or `spanish`),
+    /// optionally suffixed with `+zero` to also recognize zero, or from the path of a
+    /// `word=digit` file.
+    fn from_spec(spec: &str) -> Result<Self, String> {
+        let (name, with_zero) = match spec.strip_suffix("+zero") {
+            Some(name) => (name, true),
+            None => (spec, false),
+        };
+        match Language::from_name(name) {
+            Some(language) => Ok(Self::builtin(language, with_zero)),
+            None => Self::parse(
+                &std::fs::read_to_string(spec)
+                    .map_err(|err| format!("cannot read vocabulary {:?}: {}", spec, err))?,
+            ),
+        }
+    }
 
+    /// The words to search for, digits included.
+    fn entries(&self) -> Vec<(&str, char)> {
+        let with_zero = self.words.iter().any(|(_, digit)| *digit == '0');
+        let digits = if with_zero { &DIGITS[..] } else { &DIGITS[1..] };
+        digits
+            .iter()
+            .copied()
+            .chain(
+                self.words
+                    .iter()
+                    .map(|(word, digit)| (word.as_str(), *digit)),
+            )
+            .collect()
+    }
+}
+
+#[derive(Clone, Copy)]
 struct PlacedDigit {
     digit: char,
     position: usize,
 }
 
-fn get_first_and_last_digits(line: &str) -> (char, char) {
-    let mut first_digit: Option<PlacedDigit> = None;
-    let mut last_digit: Option<PlacedDigit> = None;
-    let all_digits = HashMap::from([
-        ("one", '1'),
-        ("two", '2'),
-        ("three", '3'),
-        ("four", '4'),
-        ("five", '5'),
-        ("six", '6'),
-        ("seven", '7'),
-        ("eight", '8'),
-        ("nine", '9'),
-        ("1", '1'),
-        ("2", '2'),
-        ("3", '3'),
-        ("4", '4'),
-        ("5", '5'),
-        ("6", '6'),
-        ("7", '7'),
-        ("8", '8'),
-        ("9", '9'),
-    ]);
-    for (search, digit) in &all_digits {
-        if let Some(position) = line.find(search) {
-            match first_digit {
-                None => {
-                    first_digit = Some(PlacedDigit {
-                        digit: *digit,
-                        position,
-                    })
-                }
-                Some(ref mut first_digit) => {
-                    if first_digit.position > position {
-                        first_digit.digit = *digit;
-                        first_digit.position = position;
+#[derive(Default)]
+struct Node {
+    next: HashMap<u8, usize>,
+    fail: usize,
+    /// The longest word ending at this node, as its length and digit.
+    output: Option<(usize, char)>,
+}
+
+/// An Aho-Corasick automaton matching a set of words in a single pass.
+struct Automaton {
+    nodes: Vec<Node>,
+    max_length: usize,
+}
+
+impl Automaton {
+    fn new(words: impl Iterator<Item = (Vec<u8>, char)>) -> Self {
+        let mut nodes = vec![Node::default()];
+        let mut max_length = 0;
+        for (word, digit) in words {
+            let mut node = 0;
+            for byte in &word {
+                node = match nodes[node].next.get(byte) {
+                    Some(next) => *next,
+                    None => {
+                        nodes.push(Node::default());
+                        let next = nodes.len() - 1;
+                        nodes[node].next.insert(*byte, next);
+                        next
                     }
+                };
+            }
+            nodes[node].output = Some((word.len(), digit));
+            max_length = max_length.max(word.len());
+        }
+
+        // Compute failure links breadth-first, so that parents are always done before children
+        let mut queue = nodes[0].next.values().copied().collect::<VecDeque<_>>();
+        while let Some(node) = queue.pop_front() {
+            let children = nodes[node]
+                .next
+                .iter()
+                .map(|(byte, child)| (*byte, *child))
+                .collect::<Vec<_>>();
+            for (byte, child) in children {
+                let mut fail = nodes[node].fail;
+                while fail != 0 && !nodes[fail].next.contains_key(&byte) {
+                    fail = nodes[fail].fail;
+                }
+                let fail = nodes[fail].next.get(&byte).copied().unwrap_or(0);
+                nodes[child].fail = fail;
+                if nodes[child].output.is_none() {
+                    nodes[child].output = nodes[fail].output;
                 }
+                queue.push_back(child);
+            }
+        }
+
+        Self { nodes, max_length }
+    }
+
+    fn step(&self, mut node: usize, byte: u8) -> usize {
+        while node != 0 && !self.nodes[node].next.contains_key(&byte) {
+            node = self.nodes[node].fail;
+        }
+        self.nodes[node].next.get(&byte).copied().unwrap_or(0)
+    }
+
+    /// Find the word ending the earliest in `bytes`. If several words end there, the longest one
+    /// wins.
+    fn find_first_ending(&self, bytes: impl Iterator<Item = u8>) -> Option<char> {
+        let mut node = 0;
+        for byte in bytes {
+            node = self.step(node, byte);
+            if let Some((_, digit)) = self.nodes[node].output {
+                return Some(digit);
             }
         }
-        if let Some(position) = line.rfind(search) {
-            match last_digit {
-                None => {
-                    last_digit = Some(PlacedDigit {
-                        digit: *digit,
-                        position,
-                    })
+        None
+    }
+
+    /// Find the word starting the earliest in `bytes`. If several words start there, the longest
+    /// one wins.
+    fn find_first_starting(&self, bytes: impl Iterator<Item = u8>) -> Option<PlacedDigit> {
+        let mut node = 0;
+        let mut first: Option<PlacedDigit> = None;
+        for (index, byte) in bytes.enumerate() {
+            if let Some(first) = first {
+                // No word starting before the one found can end here
+                if index >= first.position + self.max_length {
+                    break;
                 }
-                Some(ref mut last_digit) => {
-                    if last_digit.position < position {
-                        last_digit.digit = *digit;
-                        last_digit.position = position;
-                    }
+            }
+            node = self.step(node, byte);
+            if let Some((length, digit)) = self.nodes[node].output {
+                let position = index + 1 - length;
+                // Words ending later at the same position are longer, so they win
+                if first.is_none_or(|first| position <= first.position) {
+                    first = Some(PlacedDigit { digit, position });
                 }
             }
         }
+        first
     }
-    (first_digit.unwrap().digit, last_digit.unwrap().digit)
 }
 
-fn sum_calibration_values(path: &str) -> u32 {
+/// Finds the first and last digits of calibration lines, from a table of words.
+///
+/// Words may overlap, e.g. "twone" starts with a 2 and ends with a 1.
+struct DigitScanner {
+    forward: Automaton,
+    backward: Automaton,
+}
+
+impl DigitScanner {
+    fn new(words: &[(&str, char)]) -> Self {
+        Self {
+            forward: Automaton::new(
+                words
+                    .iter()
+                    .map(|(word, digit)| (word.bytes().collect(), *digit)),
+            ),
+            backward: Automaton::new(
+                words
+                    .iter()
+                    .map(|(word, digit)| (word.bytes().rev().collect(), *digit)),
+            ),
+        }
+    }
+
+    fn from_vocabulary(vocabulary: &Vocabulary) -> Self {
+        Self::new(&vocabulary.entries())
+    }
+
+    fn digits_only() -> Self {
+        Self::from_vocabulary(&Vocabulary::default())
+    }
+
+    fn first_and_last_digits(&self, line: &str) -> Result<(char, char), String> {
+        let no_digit = || format!("no digit found in line: {:?}", line);
+        let first = self
+            .forward
+            .find_first_starting(line.bytes())
+            .ok_or_else(no_digit)?;
+        // The word starting last is the first one to end when reading backwards
+        let last = self
+            .backward
+            .find_first_ending(line.bytes().rev())
+            .ok_or_else(no_digit)?;
+        Ok((first.digit, last))
+    }
+}
+
+fn sum_calibration_values(path: &str, scanner: &DigitScanner) -> Result<u32, String> {
     std::fs::read_to_string(path)
-        .unwrap()
+        .map_err(|err| format!("cannot read {:?}: {}", path, err))?
         .lines()
         .map(|line| {
-            let mut combined_digits = String::new();
-            let (first_digit, last_digit) = get_first_and_last_digits(line);
-            combined_digits.push(first_digit);
-            combined_digits.push(last_digit);
-            combined_digits.parse::<u32>().unwrap()
+            let (first_digit, last_digit) = scanner.first_and_last_digits(line)?;
+            Ok(first_digit.to_digit(10).unwrap() * 10 + last_digit.to_digit(10).unwrap())
         })
-        .reduce(|a, b| a + b)
-        .unwrap()
+        .sum()
 }
 
 impl Problem for Day1 {
     fn check(&self) {
         println!(
             "Calibration values sum: {}",
-            sum_calibration_values("src/day_1/example.txt")
+            sum_calibration_values("src/day_1/example.txt", &DigitScanner::digits_only()).unwrap()
         );
     }
 
     fn solve(&self) {
+        println!(
+            "Calibration values sum (digits only): {}",
+            sum_calibration_values("src/day_1/input.txt", &DigitScanner::digits_only()).unwrap()
+        );
         println!(
             "Calibration values sum: {}",
-            sum_calibration_values("src/day_1/input.txt")
+            sum_calibration_values(
+                "src/day_1/input.txt",
+                &DigitScanner::from_vocabulary(&self.vocabulary)
+            )
+            .unwrap()
         );
     }
 }
@@ -105,17 +344,86 @@ mod tests {
 
     #[test]
     fn test_get_first_and_last_digits() {
-        assert_eq!(get_first_and_last_digits("1"), ('1', '1'));
-        assert_eq!(get_first_and_last_digits("12"), ('1', '2'));
-        assert_eq!(get_first_and_last_digits("34"), ('3', '4'));
-        assert_eq!(get_first_and_last_digits("56"), ('5', '6'));
-        assert_eq!(get_first_and_last_digits("78"), ('7', '8'));
-        assert_eq!(get_first_and_last_digits("9one"), ('9', '1'));
-        assert_eq!(get_first_and_last_digits("twothree"), ('2', '3'));
-        assert_eq!(get_first_and_last_digits("fourfive"), ('4', '5'));
-        assert_eq!(get_first_and_last_digits("sixseven"), ('6', '7'));
-        assert_eq!(get_first_and_last_digits("eightnine"), ('8', '9'));
-        assert_eq!(get_first_and_last_digits("footballone9"), ('1', '9'));
-        assert_eq!(get_first_and_last_digits("12345678901"), ('1', '1'));
+        let scanner = DigitScanner::from_vocabulary(&Vocabulary::builtin(Language::English, false));
+        let get_first_and_last_digits = |line| scanner.first_and_last_digits(line);
+        assert_eq!(get_first_and_last_digits("1"), Ok(('1', '1')));
+        assert_eq!(get_first_and_last_digits("12"), Ok(('1', '2')));
+        assert_eq!(get_first_and_last_digits("34"), Ok(('3', '4')));
+        assert_eq!(get_first_and_last_digits("56"), Ok(('5', '6')));
+        assert_eq!(get_first_and_last_digits("78"), Ok(('7', '8')));
+        assert_eq!(get_first_and_last_digits("9one"), Ok(('9', '1')));
+        assert_eq!(get_first_and_last_digits("twothree"), Ok(('2', '3')));
+        assert_eq!(get_first_and_last_digits("fourfive"), Ok(('4', '5')));
+        assert_eq!(get_first_and_last_digits("sixseven"), Ok(('6', '7')));
+        assert_eq!(get_first_and_last_digits("eightnine"), Ok(('8', '9')));
+        assert_eq!(get_first_and_last_digits("footballone9"), Ok(('1', '9')));
+        assert_eq!(get_first_and_last_digits("12345678901"), Ok(('1', '1')));
+    }
+
+    #[test]
+    fn test_overlapping_words() {
+        let scanner = DigitScanner::from_vocabulary(&Vocabulary::builtin(Language::English, false));
+        assert_eq!(scanner.first_and_last_digits("twone"), Ok(('2', '1')));
+        assert_eq!(scanner.first_and_last_digits("eightwo"), Ok(('8', '2')));
+        assert_eq!(scanner.first_and_last_digits("oneight"), Ok(('1', '8')));
+        assert_eq!(scanner.first_and_last_digits("xtwonex"), Ok(('2', '1')));
+        assert_eq!(scanner.first_and_last_digits("seven"), Ok(('7', '7')));
+    }
+
+    #[test]
+    fn test_digits_only() {
+        let scanner = DigitScanner::digits_only();
+        assert_eq!(scanner.first_and_last_digits("two1nine3x"), Ok(('1', '3')));
+        assert!(scanner.first_and_last_digits("twone").is_err());
+    }
+
+    #[test]
+    fn test_contained_words() {
+        // "b" ends before "abc", but "abc" starts first and "b" starts last
+        let scanner = DigitScanner::new(&[("abc", '1'), ("b", '2')]);
+        assert_eq!(scanner.first_and_last_digits("xabcx"), Ok(('1', '2')));
+        let scanner = DigitScanner::new(&[("abc", '1'), ("c", '2'), ("bc", '3')]);
+        assert_eq!(scanner.first_and_last_digits("abc"), Ok(('1', '2')));
+        let scanner = DigitScanner::new(&[("ab", '1'), ("abc", '2')]);
+        assert_eq!(scanner.first_and_last_digits("abc"), Ok(('2', '2')));
+    }
+
+    #[test]
+    fn test_builtin_vocabularies() {
+        let scanner = DigitScanner::from_vocabulary(&Vocabulary::builtin(Language::French, false));
+        assert_eq!(scanner.first_and_last_digits("xdeuxhuitx"), Ok(('2', '8')));
+        assert_eq!(scanner.first_and_last_digits("zéro7"), Ok(('7', '7')));
+        let scanner = DigitScanner::from_vocabulary(&Vocabulary::builtin(Language::German, true));
+        assert_eq!(
+            scanner.first_and_last_digits("nulleinsfünf"),
+            Ok(('0', '5'))
+        );
+        assert_eq!(scanner.first_and_last_digits("a0b"), Ok(('0', '0')));
+        let scanner = DigitScanner::from_vocabulary(&Vocabulary::builtin(Language::Spanish, false));
+        assert_eq!(scanner.first_and_last_digits("unodos"), Ok(('1', '2')));
+        assert!(scanner.first_and_last_digits("cero").is_err());
+    }
+
+    #[test]
+    fn test_parse_vocabulary() {
+        let vocabulary = Vocabulary::parse("# roman\n\ni=1\n v = 5 \n").unwrap();
+        assert_eq!(
+            vocabulary.words,
+            vec![("i".to_string(), '1'), ("v".to_string(), '5')]
+        );
+        let scanner = DigitScanner::from_vocabulary(&vocabulary);
+        assert_eq!(scanner.first_and_last_digits("xvix"), Ok(('5', '1')));
+        assert!(Vocabulary::parse("one").is_err());
+        assert!(Vocabulary::parse("one=12").is_err());
+        assert!(Vocabulary::parse("=1").is_err());
+    }
+
+    #[test]
+    fn test_vocabulary_from_spec() {
+        assert_eq!(
+            Vocabulary::from_spec("german+zero"),
+            Ok(Vocabulary::builtin(Language::German, true))
+        );
+        assert!(Vocabulary::from_spec("klingon").is_err());
     }
 }
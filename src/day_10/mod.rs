@@ -1,4 +1,4 @@
-use std::{collections::HashSet, fmt::Display, i32, usize};
+use std::{collections::HashSet, fmt::Display};
 
 use aoc_2023_rust_flupke::Problem;
 
@@ -235,6 +235,40 @@ impl Problem for Day10 {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::common::geometry::Polygon;
+
+    /// The loop as a polygon, with the tiles in walking order as vertices.
+    fn loop_polygon(map: &Map) -> Polygon {
+        let start_position = map.find_start();
+        let mut position = start_position;
+        let mut direction = map.start_tile.connected_tiles().next().unwrap();
+        let mut vertices = Vec::new();
+        loop {
+            vertices.push(Vector {
+                x: position.x as i64,
+                y: position.y as i64,
+            });
+            position += direction;
+            if position == start_position {
+                return Polygon::new(vertices);
+            }
+            direction = map.tiles.get(position).move_through(direction);
+        }
+    }
+
+    #[test]
+    fn test_enclosed_area_matches_polygon() {
+        for (input, start_tile, area) in [
+            (include_str!("example.txt"), '|', 9),
+            (include_str!("simple_loop.txt"), 'F', 1),
+            (include_str!("input.txt"), '-', 415),
+        ] {
+            let map = Map::parse(input, Tile::from_char(start_tile));
+            let polygon = loop_polygon(&map);
+            assert_eq!(polygon.interior_points(), area);
+            assert_eq!(polygon.interior_points(), map.enclosed_area() as i64);
+        }
+    }
 
     #[test]
     fn test_get_enclosed_area_of_complex_loop() {
@@ -6,7 +6,7 @@ use crate::common::{
     grid::{Grid, NestedIterator},
     vector::Vector,
 };
-use std::{fmt::Display, usize};
+use std::fmt::Display;
 
 pub struct Day11;
 
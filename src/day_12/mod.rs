@@ -1,7 +1,6 @@
-use std::usize;
-
 use aoc_2023_rust_flupke::{split_numbers, Problem};
-use memoize::memoize;
+
+use crate::common::memo::Memo;
 
 pub struct Day12;
 
@@ -34,57 +33,64 @@ impl Record {
     }
 
     fn arrangements(&self) -> usize {
-        count_arrangements(self.springs.clone(), self.damaged_chunks.clone(), None)
+        count_arrangements(&self.springs, &self.damaged_chunks)
     }
 }
 
-#[memoize]
-fn count_arrangements(
-    springs: String,
-    chunk_lengths: Vec<usize>,
-    current_chunk_length: Option<usize>,
-) -> usize {
-    if springs.is_empty() {
-        return match (chunk_lengths.len(), current_chunk_length) {
-            (0, None) => 1,
-            (1, Some(current_chunk_length)) if chunk_lengths[0] == current_chunk_length => 1,
-            _ => 0,
-        };
-    }
-    if current_chunk_length.is_some() && chunk_lengths.is_empty() {
-        return 0;
-    }
+/// Count the possible arrangements of `springs` matching `chunk_lengths`.
+///
+/// Recursive calls are keyed on the position in `springs`, the position in `chunk_lengths`, and
+/// the length of the damaged chunk being read, if any.
+fn count_arrangements(springs: &str, chunk_lengths: &[usize]) -> usize {
+    let springs = springs.as_bytes();
+    let count =
+        |memo: &mut Memo<(usize, usize, Option<usize>), usize>,
+         (spring, chunk, current_chunk_length): (usize, usize, Option<usize>)| {
+            if spring == springs.len() {
+                return match (chunk_lengths.len() - chunk, current_chunk_length) {
+                    (0, None) => 1,
+                    (1, Some(current_chunk_length))
+                        if chunk_lengths[chunk] == current_chunk_length =>
+                    {
+                        1
+                    }
+                    _ => 0,
+                };
+            }
+            match current_chunk_length {
+                Some(_) if chunk == chunk_lengths.len() => return 0,
+                Some(current_chunk_length) if current_chunk_length > chunk_lengths[chunk] => {
+                    return 0
+                }
+                _ => {}
+            }
 
-    match (springs.as_bytes()[0], current_chunk_length) {
-        (b'.', Some(current_chunk_length)) if current_chunk_length != chunk_lengths[0] => 0,
-        (b'.', Some(_)) => {
-            count_arrangements(springs[1..].to_string(), chunk_lengths[1..].to_vec(), None)
-        }
-        (b'.', None) => count_arrangements(springs[1..].to_string(), chunk_lengths, None),
-        (b'#', Some(current_chunk_length)) => count_arrangements(
-            springs[1..].to_string(),
-            chunk_lengths,
-            Some(current_chunk_length + 1),
-        ),
-        (b'#', None) => count_arrangements(springs[1..].to_string(), chunk_lengths, Some(1)),
-        (b'?', Some(current_chunk_length)) => {
-            let mut total = count_arrangements(
-                springs[1..].to_string(),
-                chunk_lengths.clone(),
-                Some(current_chunk_length + 1),
-            );
-            if current_chunk_length == chunk_lengths[0] {
-                total +=
-                    count_arrangements(springs[1..].to_string(), chunk_lengths[1..].to_vec(), None);
+            match (springs[spring], current_chunk_length) {
+                (b'.', Some(current_chunk_length))
+                    if current_chunk_length != chunk_lengths[chunk] =>
+                {
+                    0
+                }
+                (b'.', Some(_)) => memo.get((spring + 1, chunk + 1, None)),
+                (b'.', None) => memo.get((spring + 1, chunk, None)),
+                (b'#', Some(current_chunk_length)) => {
+                    memo.get((spring + 1, chunk, Some(current_chunk_length + 1)))
+                }
+                (b'#', None) => memo.get((spring + 1, chunk, Some(1))),
+                (b'?', Some(current_chunk_length)) => {
+                    let mut total = memo.get((spring + 1, chunk, Some(current_chunk_length + 1)));
+                    if current_chunk_length == chunk_lengths[chunk] {
+                        total += memo.get((spring + 1, chunk + 1, None));
+                    }
+                    total
+                }
+                (b'?', None) => {
+                    memo.get((spring + 1, chunk, Some(1))) + memo.get((spring + 1, chunk, None))
+                }
+                _ => unreachable!(),
             }
-            total
-        }
-        (b'?', None) => {
-            count_arrangements(springs[1..].to_string(), chunk_lengths.clone(), Some(1))
-                + count_arrangements(springs[1..].to_string(), chunk_lengths, None)
-        }
-        _ => unreachable!(),
-    }
+        };
+    Memo::new(&count).get((0, 0, None))
 }
 
 struct RecordsList {
@@ -116,3 +122,20 @@ impl Problem for Day12 {
         println!("number of arrangements: {}", records.arrangements_sum());
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_arrangements() {
+        let records = RecordsList::from_str(include_str!("example.txt"));
+        let arrangements = records
+            .records
+            .iter()
+            .map(|record| record.arrangements())
+            .collect::<Vec<_>>();
+        assert_eq!(arrangements, vec![1, 4, 1, 1, 4, 10]);
+        assert_eq!(records.arrangements_sum(), 525152);
+    }
+}
@@ -1,6 +1,4 @@
-use std::usize;
-
-use aoc_2023_rust_flupke::Problem;
+use aoc_2023_rust_flupke::{split_blocks, Problem};
 
 use crate::common::grid::{self, NestedIterator};
 
@@ -18,7 +16,10 @@ impl Pattern {
 }
 
 fn parse(input: &str) -> Vec<Pattern> {
-    input.split("\n\n").map(grid::parse_char).collect()
+    split_blocks(input)
+        .iter()
+        .map(|block| grid::parse_char(block))
+        .collect()
 }
 
 fn search_reflection(iterator: NestedIterator<&char>) -> Option<usize> {
@@ -28,7 +29,7 @@ fn search_reflection(iterator: NestedIterator<&char>) -> Option<usize> {
     (1..previous_rows.len()).find(|index| is_symetrical_with_smudge(&previous_rows, *index))
 }
 
-fn is_symetrical_with_smudge(rows: &Vec<Vec<&char>>, index: usize) -> bool {
+fn is_symetrical_with_smudge(rows: &[Vec<&char>], index: usize) -> bool {
     let mut left = index - 1;
     let mut right = index;
     let mut num_diffs = 0;
@@ -1,4 +1,4 @@
-use std::{collections::HashSet, usize};
+use std::collections::HashSet;
 
 use aoc_2023_rust_flupke::Problem;
 use circular_buffer::CircularBuffer;
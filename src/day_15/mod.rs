@@ -1,5 +1,3 @@
-use std::usize;
-
 use aoc_2023_rust_flupke::Problem;
 
 pub struct Day15;
@@ -5,7 +5,7 @@ use rayon::prelude::*;
 
 use crate::common::{
     grid::{parse_char, Grid},
-    vector::{ConvertTo, Vector},
+    vector::Vector,
 };
 
 type HereVector = Vector<i16>;
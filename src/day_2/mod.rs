@@ -1,72 +1,330 @@
-use aoc_2023_rust_flupke::Problem;
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt::Display,
+    str::FromStr,
+};
 
-pub struct Day2;
+use aoc_2023_rust_flupke::{parse_header, Problem};
 
+pub struct Day2 {
+    /// Games file to analyze
+    input: String,
+    /// Bag the games are checked against
+    bag: Bag,
+}
+
+impl Day2 {
+    pub fn new(input: Option<String>, bag: Option<&str>) -> Result<Self, String> {
+        let bag = match bag {
+            Some(bag) => bag.parse::<Bag>()?,
+            None => Bag::new([("red", 12), ("green", 13), ("blue", 14)]),
+        };
+        Ok(Self {
+            input: input.unwrap_or("src/day_2/input.txt".to_string()),
+            bag,
+        })
+    }
+}
+
+/// A number of cubes per color, either drawn from a bag or contained in it.
+#[derive(Debug, Default, Clone, PartialEq)]
 struct Draft {
-    red: u32,
-    green: u32,
-    blue: u32,
+    cubes: BTreeMap<String, u32>,
 }
 
+type Bag = Draft;
+
 impl Draft {
-    fn new() -> Draft {
-        Draft {
-            red: 0,
-            green: 0,
-            blue: 0,
+    fn new<'a>(cubes: impl IntoIterator<Item = (&'a str, u32)>) -> Self {
+        Self {
+            cubes: cubes
+                .into_iter()
+                .map(|(color, count)| (color.to_string(), count))
+                .collect(),
         }
     }
 
-    fn power(&self) -> u32 {
-        self.red * self.green * self.blue
+    fn count(&self, color: &str) -> u32 {
+        self.cubes.get(color).copied().unwrap_or(0)
+    }
+
+    fn colors(&self) -> impl Iterator<Item = &str> {
+        self.cubes.keys().map(String::as_str)
     }
-}
 
-fn parse_draft(draft: &str) -> Draft {
-    let mut result = Draft::new();
-    for draft_entry in draft.split(',') {
-        let (number, color) = draft_entry.trim().split_once(' ').unwrap();
-        let number = number.parse::<u32>().unwrap();
-        match color {
-            "red" => result.red += number,
-            "green" => result.green += number,
-            "blue" => result.blue += number,
-            _ => panic!("Unknown color {}", color),
+    /// Product of the counts of `colors`, a missing color counting as 0.
+    fn power<'a>(&self, colors: impl IntoIterator<Item = &'a str>) -> u32 {
+        colors.into_iter().map(|color| self.count(color)).product()
+    }
+
+    /// Check if this draft can be drawn from `bag`.
+    fn fits_in(&self, bag: &Bag) -> bool {
+        self.cubes
+            .iter()
+            .all(|(color, count)| *count <= bag.count(color))
+    }
+
+    /// Grow this draft so that it contains at least the cubes of `other`.
+    fn extend_to(&mut self, other: &Draft) {
+        for (color, count) in &other.cubes {
+            let entry = self.cubes.entry(color.clone()).or_insert(0);
+            *entry = (*entry).max(*count);
         }
     }
-    result
 }
 
-fn get_game_power(line: &str) -> u32 {
-    let suffix = line.split_once(':').unwrap().1;
-    let mut min_draft = Draft::new();
-    for draft in suffix.split(';') {
-        let draft = parse_draft(draft);
-        if draft.red > min_draft.red {
-            min_draft.red = draft.red;
-        }
-        if draft.green > min_draft.green {
-            min_draft.green = draft.green;
+impl Display for Draft {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let entries = self
+            .cubes
+            .iter()
+            .map(|(color, count)| format!("{} {}", count, color))
+            .collect::<Vec<_>>();
+        write!(f, "{}", entries.join(", "))
+    }
+}
+
+impl FromStr for Draft {
+    type Err = String;
+
+    fn from_str(draft: &str) -> Result<Self, Self::Err> {
+        let mut result = Draft::default();
+        for draft_entry in draft.split(',') {
+            let (number, color) = draft_entry
+                .trim()
+                .split_once(' ')
+                .ok_or(format!("invalid draft entry: {:?}", draft_entry))?;
+            let number = number
+                .parse::<u32>()
+                .map_err(|err| format!("invalid cubes count {:?}: {}", number, err))?;
+            *result.cubes.entry(color.to_string()).or_insert(0) += number;
         }
-        if draft.blue > min_draft.blue {
-            min_draft.blue = draft.blue;
+        Ok(result)
+    }
+}
+
+#[derive(Debug)]
+struct Game {
+    id: u32,
+    drafts: Vec<Draft>,
+}
+
+impl Game {
+    fn is_possible(&self, bag: &Bag) -> bool {
+        self.impossible_drafts(bag).next().is_none()
+    }
+
+    /// The drafts that can't be drawn from `bag`, with their index in the game.
+    fn impossible_drafts<'a>(&'a self, bag: &'a Bag) -> impl Iterator<Item = (usize, &'a Draft)> {
+        self.drafts
+            .iter()
+            .enumerate()
+            .filter(|(_, draft)| !draft.fits_in(bag))
+    }
+
+    /// The smallest bag this game could have been played with.
+    fn minimum_bag(&self) -> Bag {
+        let mut bag = Bag::default();
+        for draft in &self.drafts {
+            bag.extend_to(draft);
         }
+        bag
+    }
+}
+
+impl FromStr for Game {
+    type Err = String;
+
+    fn from_str(line: &str) -> Result<Self, Self::Err> {
+        let (id_text, drafts_text) = parse_header(line)?;
+        let id = id_text
+            .strip_prefix("Game ")
+            .and_then(|id| id.parse::<u32>().ok())
+            .ok_or(format!("invalid game id: {:?}", id_text))?;
+        let drafts = drafts_text
+            .split(';')
+            .map(|draft| draft.parse::<Draft>())
+            .collect::<Result<Vec<Draft>, String>>()?;
+        Ok(Game { id, drafts })
     }
-    min_draft.power()
+}
+
+fn parse_games(input: &str) -> Result<Vec<Game>, String> {
+    input.lines().map(|line| line.parse::<Game>()).collect()
+}
+
+fn possible_games_sum(games: &[Game], bag: &Bag) -> u32 {
+    games
+        .iter()
+        .filter(|game| game.is_possible(bag))
+        .map(|game| game.id)
+        .sum()
+}
+
+/// The colors of `bag` and of the cubes drawn in `games`.
+/// The colors drawn in any of the games.
+fn all_colors(games: &[Game]) -> BTreeSet<&str> {
+    games
+        .iter()
+        .flat_map(|game| &game.drafts)
+        .flat_map(Draft::colors)
+        .collect()
+}
+
+fn minimum_bags_power_sum(games: &[Game]) -> u32 {
+    let colors = all_colors(games);
+    games
+        .iter()
+        .map(|game| game.minimum_bag().power(colors.iter().copied()))
+        .sum()
+}
+
+/// The smallest bag all the games could have been played with.
+fn smallest_common_bag(games: &[Game]) -> Bag {
+    let mut bag = Bag::default();
+    for game in games {
+        bag.extend_to(&game.minimum_bag());
+    }
+    bag
+}
+
+/// The games that can't be played with `bag`, with the drafts ruling them out.
+fn ruled_out_games<'a>(
+    games: &'a [Game],
+    bag: &'a Bag,
+) -> impl Iterator<Item = (&'a Game, Vec<(usize, &'a Draft)>)> {
+    games.iter().filter_map(|game| {
+        let drafts = game.impossible_drafts(bag).collect::<Vec<_>>();
+        (!drafts.is_empty()).then_some((game, drafts))
+    })
+}
+
+fn solve(path: &str, bag: &Bag) {
+    let games = parse_games(&std::fs::read_to_string(path).unwrap()).unwrap();
+    println!("Possible games sum: {}", possible_games_sum(&games, bag));
+    println!("Powers sum: {}", minimum_bags_power_sum(&games));
 }
 
 impl Problem for Day2 {
     fn check(&self) {
-        panic!("Not implemented");
+        solve("src/day_2/example.txt", &self.bag);
     }
 
     fn solve(&self) {
-        let powers_sum = std::fs::read_to_string("src/day_2/input.txt")
-            .unwrap()
-            .lines()
-            .map(get_game_power)
-            .reduce(|a, b| a + b)
+        solve("src/day_2/input.txt", &self.bag);
+    }
+
+    fn analyze(&self) {
+        let games = parse_games(&std::fs::read_to_string(&self.input).unwrap()).unwrap();
+        let smallest_bag = smallest_common_bag(&games);
+        println!(
+            "Smallest bag for all {} games: {} (power {})",
+            games.len(),
+            smallest_bag,
+            smallest_bag.power(all_colors(&games))
+        );
+        println!("Games ruled out by bag {}:", self.bag);
+        let mut ruled_out_count = 0;
+        for (game, drafts) in ruled_out_games(&games, &self.bag) {
+            ruled_out_count += 1;
+            for (index, draft) in drafts {
+                println!("  game {}, draft {}: {}", game.id, index + 1, draft);
+            }
+        }
+        println!(
+            "{} possible games, {} ruled out",
+            games.len() - ruled_out_count,
+            ruled_out_count
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_game() {
+        let game = "Game 12: 3 blue, 4 red; 2 yellow".parse::<Game>().unwrap();
+        assert_eq!(game.id, 12);
+        assert_eq!(
+            game.drafts,
+            vec![
+                Draft::new([("blue", 3), ("red", 4)]),
+                Draft::new([("yellow", 2)])
+            ]
+        );
+        assert!("Game x: 3 blue".parse::<Game>().is_err());
+        assert!("Game 1: blue".parse::<Game>().is_err());
+    }
+
+    #[test]
+    fn test_feasibility() {
+        let games = parse_games(include_str!("example.txt")).unwrap();
+        let bag = Bag::new([("red", 12), ("green", 13), ("blue", 14)]);
+        assert_eq!(possible_games_sum(&games, &bag), 8);
+        let impossible = games[2].impossible_drafts(&bag).collect::<Vec<_>>();
+        assert_eq!(
+            impossible,
+            vec![(0, &Draft::new([("green", 8), ("blue", 6), ("red", 20)]))]
+        );
+    }
+
+    #[test]
+    fn test_minimum_bag() {
+        let games = parse_games(include_str!("example.txt")).unwrap();
+        assert_eq!(
+            games[0].minimum_bag(),
+            Bag::new([("red", 4), ("green", 2), ("blue", 6)])
+        );
+        assert_eq!(minimum_bags_power_sum(&games), 2286);
+    }
+
+    #[test]
+    fn test_other_colors() {
+        let game = "Game 1: 2 pink, 1 red; 3 pink".parse::<Game>().unwrap();
+        assert!(!game.is_possible(&Bag::new([("red", 12)])));
+        assert!(game.is_possible(&Bag::new([("red", 1), ("pink", 3)])));
+        // Green and blue cubes are never drawn
+        assert_eq!(game.minimum_bag().power(["red", "green", "blue"]), 0);
+        assert_eq!(game.minimum_bag().power(["red", "pink"]), 3);
+        assert_eq!(Bag::default().power(["red"]), 0);
+        assert_eq!(minimum_bags_power_sum(&[game]), 3);
+    }
+
+    #[test]
+    fn test_extra_bag_color() {
+        let games = parse_games(include_str!("example.txt")).unwrap();
+        let bag = "12 red, 13 green, 14 blue, 1 yellow"
+            .parse::<Bag>()
             .unwrap();
-        println!("Possible games sum: {}", powers_sum);
+        assert_eq!(possible_games_sum(&games, &bag), 8);
+        // Yellow cubes are in the bag but never drawn
+        assert_eq!(all_colors(&games), BTreeSet::from(["blue", "green", "red"]));
+        assert_eq!(minimum_bags_power_sum(&games), 2286);
+    }
+
+    #[test]
+    fn test_smallest_common_bag() {
+        let games = parse_games(include_str!("example.txt")).unwrap();
+        let bag = smallest_common_bag(&games);
+        assert_eq!(bag, Bag::new([("red", 20), ("green", 13), ("blue", 15)]));
+        assert!(games.iter().all(|game| game.is_possible(&bag)));
+        assert_eq!(bag.to_string(), "15 blue, 13 green, 20 red");
+    }
+
+    #[test]
+    fn test_ruled_out_games() {
+        let games = parse_games(include_str!("example.txt")).unwrap();
+        let bag = "12 red, 13 green, 14 blue".parse::<Bag>().unwrap();
+        let ruled_out = ruled_out_games(&games, &bag)
+            .map(|(game, drafts)| {
+                (
+                    game.id,
+                    drafts.iter().map(|(index, _)| *index).collect::<Vec<_>>(),
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(ruled_out, vec![(3, vec![0]), (4, vec![2])]);
     }
 }
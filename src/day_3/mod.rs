@@ -1,13 +1,30 @@
-use std::collections::HashSet;
+use std::{collections::BTreeSet, ops::RangeInclusive, str::FromStr};
 
 use aoc_2023_rust_flupke::Problem;
 
-pub struct Day3;
+use crate::common::grid::{parse_char, Grid, GridCoordinates};
+
+pub struct Day3 {
+    /// Rule deciding which symbols are gears
+    gear_rule: GearRule,
+}
+
+impl Day3 {
+    pub fn new(gear_rule: Option<&str>) -> Result<Self, String> {
+        Ok(Self {
+            gear_rule: match gear_rule {
+                Some(gear_rule) => gear_rule.parse()?,
+                None => GearRule::standard(),
+            },
+        })
+    }
+}
 
 struct Schematic {
-    lines: Vec<String>,
-    width: usize,
-    height: usize,
+    parts: Vec<PlacedPart>,
+    symbols: Vec<PlacedSymbol>,
+    /// The index in `parts` of the part covering each cell, if any.
+    part_at: Grid<Option<usize>>,
 }
 
 #[derive(Debug, Clone, Hash, Eq, PartialEq)]
@@ -15,7 +32,7 @@ struct PlacedPart {
     x_start: usize,
     x_end: usize,
     y: usize,
-    value: String,
+    value: u32,
 }
 
 #[derive(Debug, Clone)]
@@ -25,106 +42,229 @@ struct PlacedSymbol {
     symbol: char,
 }
 
-impl Schematic {
-    fn new(text: &str) -> Self {
-        let lines = text
-            .lines()
-            .map(|line| line.to_string())
-            .collect::<Vec<String>>();
-        let width = lines[0].len();
-        let height = lines.len();
+/// How the values of the parts around a gear combine into its value.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Combine {
+    Product,
+    Sum,
+    Max,
+}
+
+impl Combine {
+    /// The combined value, or `None` if it overflows.
+    fn apply(&self, mut values: impl Iterator<Item = u64>) -> Option<u64> {
+        match self {
+            Self::Product => values.try_fold(1u64, |product, value| product.checked_mul(value)),
+            Self::Sum => values.try_fold(0u64, |sum, value| sum.checked_add(value)),
+            Self::Max => Some(values.max().unwrap_or(0)),
+        }
+    }
+}
+
+impl FromStr for Combine {
+    type Err = String;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        match input {
+            "product" => Ok(Self::Product),
+            "sum" => Ok(Self::Sum),
+            "max" => Ok(Self::Max),
+            _ => Err(format!("invalid combination: {:?}", input)),
+        }
+    }
+}
+
+/// Describes which symbols are gears: one of `symbols`, surrounded by a number of parts in
+/// `parts_count`.
+#[derive(Debug, Clone)]
+struct GearRule {
+    symbols: Vec<char>,
+    parts_count: RangeInclusive<usize>,
+    combine: Combine,
+}
+
+impl GearRule {
+    fn new(symbols: &str, parts_count: RangeInclusive<usize>, combine: Combine) -> Self {
         Self {
-            lines,
-            width,
-            height,
+            symbols: symbols.chars().collect(),
+            parts_count,
+            combine,
+        }
+    }
+
+    /// A `*` touching exactly two parts, whose ratio is the product of the parts.
+    fn standard() -> Self {
+        Self::new("*", 2..=2, Combine::Product)
+    }
+}
+
+/// Parse a `symbols:parts count:combination` rule, where the parts count is either a number or
+/// a `min-max` range, e.g. `*:2:product` for the standard rule.
+impl FromStr for GearRule {
+    type Err = String;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        let &[symbols, parts_count, combine] = input.split(':').collect::<Vec<_>>().as_slice()
+        else {
+            return Err(format!("invalid gear rule: {:?}", input));
+        };
+        let parse_count = |count: &str| {
+            count
+                .parse::<usize>()
+                .map_err(|err| format!("invalid parts count {:?}: {}", count, err))
+        };
+        let parts_count = match parts_count.split_once('-') {
+            Some((min, max)) => parse_count(min)?..=parse_count(max)?,
+            None => parse_count(parts_count)?..=parse_count(parts_count)?,
+        };
+        if parts_count.is_empty() {
+            return Err(format!(
+                "invalid parts count range: {}-{}",
+                parts_count.start(),
+                parts_count.end()
+            ));
         }
+        if symbols.is_empty() {
+            return Err(format!("no gear symbols: {:?}", input));
+        }
+        Ok(Self::new(symbols, parts_count, combine.parse()?))
     }
+}
 
-    fn parse(&self) -> (Vec<PlacedPart>, Vec<PlacedSymbol>) {
+/// A symbol matching a [`GearRule`].
+#[derive(Debug, Clone, PartialEq)]
+struct Gear {
+    x: usize,
+    y: usize,
+    symbol: char,
+    parts: Vec<u32>,
+    value: u64,
+}
+
+impl Schematic {
+    fn new(text: &str) -> Self {
+        let grid = parse_char(text);
         let mut parts = Vec::new();
         let mut symbols = Vec::new();
-        let mut current_part: Option<PlacedPart> = None;
+        let mut part_at = Grid::from_data(vec![None; grid.data.len()], grid.width, grid.height);
 
-        fn push_current_part(current_part: &mut Option<PlacedPart>, parts: &mut Vec<PlacedPart>) {
-            if let Some(part) = current_part.take() {
-                parts.push(part);
+        for (x, y) in grid.iter_coords::<GridCoordinates>() {
+            let char = *grid.get((x, y));
+            if let Some(digit) = char.to_digit(10) {
+                let previous_part = if x > 0 {
+                    *part_at.get((x - 1, y))
+                } else {
+                    None
+                };
+                match previous_part {
+                    Some(index) => {
+                        let part: &mut PlacedPart = &mut parts[index];
+                        part.x_end = x;
+                        part.value = part.value * 10 + digit;
+                        part_at.set((x, y), Some(index));
+                    }
+                    None => {
+                        parts.push(PlacedPart {
+                            x_start: x,
+                            x_end: x,
+                            y,
+                            value: digit,
+                        });
+                        part_at.set((x, y), Some(parts.len() - 1));
+                    }
+                }
+            } else if char != '.' {
+                symbols.push(PlacedSymbol { x, y, symbol: char });
             }
         }
 
-        for y in 0..self.height {
-            for x in 0..self.width {
-                let char = self.lines[y].chars().nth(x).unwrap();
-                if char.is_ascii_digit() {
-                    match current_part {
-                        None => {
-                            current_part = Some(PlacedPart {
-                                x_start: x,
-                                x_end: x,
-                                y,
-                                value: char.to_string(),
-                            })
-                        }
-                        Some(ref mut current_part) => {
-                            current_part.x_end = x;
-                            current_part.value.push(char);
-                        }
-                    }
-                } else if char == '.' {
-                    push_current_part(&mut current_part, &mut parts);
-                } else {
-                    push_current_part(&mut current_part, &mut parts);
-                    symbols.push(PlacedSymbol { x, y, symbol: char });
-                }
+        Self {
+            parts,
+            symbols,
+            part_at,
+        }
+    }
+
+    /// The indices of the parts around `symbol`.
+    fn adjacent_parts(&self, symbol: &PlacedSymbol) -> BTreeSet<usize> {
+        self.part_at
+            .neighbors((symbol.x, symbol.y))
+            .filter_map(|position: GridCoordinates| *self.part_at.get(position))
+            .collect()
+    }
+
+    fn parts_numbers_sum(&self) -> u32 {
+        let mut is_part_number = vec![false; self.parts.len()];
+        for symbol in &self.symbols {
+            for index in self.adjacent_parts(symbol) {
+                is_part_number[index] = true;
             }
-            push_current_part(&mut current_part, &mut parts);
         }
-        (parts, symbols)
-    }
-}
-
-fn get_gears_ratios_sum(text: &str) -> u32 {
-    let schematic = Schematic::new(text);
-    let (parts, symbols) = schematic.parse();
-    let mut sum = 0;
-
-    for symbol in symbols {
-        if symbol.symbol == '*' {
-            let mut touched_parts = HashSet::new();
-            for dy in -1..=1 {
-                for dx in -1..=1 {
-                    let x = symbol.x as i32 + dx;
-                    let y = symbol.y as i32 + dy;
-                    for part in &parts {
-                        if part.x_start as i32 <= x && x <= part.x_end as i32 && part.y as i32 == y
-                        {
-                            touched_parts.insert(part);
-                        }
-                    }
+        self.parts
+            .iter()
+            .zip(is_part_number)
+            .filter(|(_, is_part_number)| *is_part_number)
+            .map(|(part, _)| part.value)
+            .sum()
+    }
+
+    /// The gears matching `rule`, or an error for the gears whose value overflows.
+    fn gears<'a>(&'a self, rule: &'a GearRule) -> impl Iterator<Item = Result<Gear, String>> + 'a {
+        self.symbols
+            .iter()
+            .filter(|symbol| rule.symbols.contains(&symbol.symbol))
+            .filter_map(|symbol| {
+                let touched_parts = self.adjacent_parts(symbol);
+                if !rule.parts_count.contains(&touched_parts.len()) {
+                    return None;
                 }
-            }
-            if touched_parts.len() == 2 {
-                sum += touched_parts
+                let parts = touched_parts
                     .iter()
-                    .map(|part| part.value.parse::<u32>().unwrap())
-                    .product::<u32>();
-            }
-        }
+                    .map(|index| self.parts[*index].value)
+                    .collect::<Vec<_>>();
+                let value = rule
+                    .combine
+                    .apply(parts.iter().map(|value| *value as u64))
+                    .ok_or(format!(
+                        "too large gear value at ({}, {})",
+                        symbol.x, symbol.y
+                    ));
+                Some(value.map(|value| Gear {
+                    x: symbol.x,
+                    y: symbol.y,
+                    symbol: symbol.symbol,
+                    parts,
+                    value,
+                }))
+            })
+    }
+
+    fn gears_ratios_sum(&self, rule: &GearRule) -> Result<u64, String> {
+        self.gears(rule).try_fold(0u64, |total, gear| {
+            total
+                .checked_add(gear?.value)
+                .ok_or("too large gears ratio sum".to_string())
+        })
     }
+}
 
-    sum
+fn solve(path: &str, gear_rule: &GearRule) {
+    let text = std::fs::read_to_string(path).unwrap();
+    let schematic = Schematic::new(&text);
+    println!("Part numbers sum: {}", schematic.parts_numbers_sum());
+    println!(
+        "Gears ratio sum: {}",
+        schematic.gears_ratios_sum(gear_rule).unwrap()
+    );
 }
 
 impl Problem for Day3 {
     fn check(&self) {
-        let text = std::fs::read_to_string("src/day_3/example.txt").unwrap();
-        let ratios_sum = get_gears_ratios_sum(&text);
-        println!("Gears ratio sum: {}", ratios_sum);
+        solve("src/day_3/example.txt", &self.gear_rule);
     }
 
     fn solve(&self) {
-        let text = std::fs::read_to_string("src/day_3/input.txt").unwrap();
-        let ratios_sum = get_gears_ratios_sum(&text);
-        println!("Gears ratio sum: {}", ratios_sum);
+        solve("src/day_3/input.txt", &self.gear_rule);
     }
 }
 
@@ -135,8 +275,121 @@ mod tests {
     #[test]
     fn test_parse() {
         let schematic = Schematic::new("1..2");
-        let (parts, symbols) = schematic.parse();
-        assert_eq!(symbols.len(), 0);
-        assert_eq!(parts.len(), 2);
+        assert_eq!(schematic.symbols.len(), 0);
+        assert_eq!(schematic.parts.len(), 2);
+    }
+
+    #[test]
+    fn test_parse_parts_at_line_boundaries() {
+        let schematic = Schematic::new("..12\n34*.");
+        assert_eq!(
+            schematic.parts,
+            vec![
+                PlacedPart {
+                    x_start: 2,
+                    x_end: 3,
+                    y: 0,
+                    value: 12
+                },
+                PlacedPart {
+                    x_start: 0,
+                    x_end: 1,
+                    y: 1,
+                    value: 34
+                },
+            ]
+        );
+        assert_eq!(*schematic.part_at.get((3, 0)), Some(0));
+        assert_eq!(*schematic.part_at.get((2, 1)), None);
+    }
+
+    #[test]
+    fn test_example() {
+        let schematic = Schematic::new(include_str!("example.txt"));
+        assert_eq!(schematic.parts_numbers_sum(), 4361);
+        assert_eq!(
+            schematic.gears_ratios_sum(&GearRule::standard()),
+            Ok(467835)
+        );
+    }
+
+    #[test]
+    fn test_gears() {
+        let schematic = Schematic::new(include_str!("example.txt"));
+        assert_eq!(
+            schematic
+                .gears(&GearRule::standard())
+                .collect::<Result<Vec<_>, _>>(),
+            Ok(vec![
+                Gear {
+                    x: 3,
+                    y: 1,
+                    symbol: '*',
+                    parts: vec![467, 35],
+                    value: 16345
+                },
+                Gear {
+                    x: 5,
+                    y: 8,
+                    symbol: '*',
+                    parts: vec![755, 598],
+                    value: 451490
+                },
+            ])
+        );
+    }
+
+    #[test]
+    fn test_custom_gear_rules() {
+        let schematic = Schematic::new(include_str!("example.txt"));
+        let single_parts = GearRule::new("*#+$", 1..=1, Combine::Sum);
+        assert_eq!(
+            schematic
+                .gears(&single_parts)
+                .map(|gear| gear.map(|gear| (gear.symbol, gear.value)))
+                .collect::<Vec<_>>(),
+            vec![
+                Ok(('#', 633)),
+                Ok(('*', 617)),
+                Ok(('+', 592)),
+                Ok(('$', 664))
+            ]
+        );
+        let any_parts = GearRule::new("*", 1..=3, Combine::Max);
+        assert_eq!(schematic.gears_ratios_sum(&any_parts), Ok(467 + 617 + 755));
+    }
+
+    #[test]
+    fn test_gear_value_overflow() {
+        let schematic = Schematic::new("9999999.9999999\n.......*.......\n9999999.9999999");
+        let rule = GearRule::new("*", 1..=8, Combine::Product);
+        assert_eq!(
+            schematic.gears_ratios_sum(&rule),
+            Err("too large gear value at (7, 1)".to_string())
+        );
+        let rule = GearRule::new("*", 1..=8, Combine::Sum);
+        assert_eq!(schematic.gears_ratios_sum(&rule), Ok(4 * 9999999));
+    }
+
+    #[test]
+    fn test_parse_gear_rule() {
+        let schematic = Schematic::new(include_str!("example.txt"));
+        let rule = "*:1-3:max".parse::<GearRule>().unwrap();
+        assert_eq!(rule.parts_count, 1..=3);
+        assert_eq!(schematic.gears_ratios_sum(&rule), Ok(467 + 617 + 755));
+        let standard = "*:2:product".parse::<GearRule>().unwrap();
+        assert_eq!(schematic.gears_ratios_sum(&standard), Ok(467835));
+        assert_eq!(
+            "#+:1:sum".parse::<GearRule>().unwrap().symbols,
+            vec!['#', '+']
+        );
+        assert!("*:2".parse::<GearRule>().is_err());
+        assert!("*:x:sum".parse::<GearRule>().is_err());
+        assert!("*:2:mean".parse::<GearRule>().is_err());
+        assert!(":2:sum".parse::<GearRule>().is_err());
+        assert_eq!(
+            "*:3-1:sum".parse::<GearRule>().unwrap_err(),
+            "invalid parts count range: 3-1"
+        );
     }
 }
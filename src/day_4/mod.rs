@@ -1,71 +1,343 @@
-use aoc_2023_rust_flupke::{split_numbers, Problem};
+use std::str::FromStr;
 
-pub struct Day4;
+use aoc_2023_rust_flupke::{parse_header, try_split_numbers, Problem};
+
+pub struct Day4 {
+    /// Cards file to analyze
+    input: String,
+    /// Print the analysis as JSON instead of a table
+    json: bool,
+}
+
+impl Day4 {
+    pub fn new(input: Option<String>, json: bool) -> Self {
+        Self {
+            input: input.unwrap_or("src/day_4/input.txt".to_string()),
+            json,
+        }
+    }
+}
+
+/// A set of `u8` numbers, stored as a bitset.
+#[derive(Debug, Default, Clone, PartialEq)]
+struct NumberSet([u64; 4]);
+
+impl NumberSet {
+    fn insert(&mut self, number: u8) {
+        self.0[number as usize / 64] |= 1 << (number % 64);
+    }
+
+    fn contains(&self, number: u8) -> bool {
+        self.0[number as usize / 64] & (1 << (number % 64)) != 0
+    }
+}
+
+impl FromIterator<u8> for NumberSet {
+    fn from_iter<I: IntoIterator<Item = u8>>(iter: I) -> Self {
+        let mut set = Self::default();
+        for number in iter {
+            set.insert(number);
+        }
+        set
+    }
+}
 
 #[derive(Debug)]
 struct Card {
-    _id: u8,
-    winning_numbers: Vec<u8>,
+    id: u32,
+    winning_numbers: NumberSet,
     numbers: Vec<u8>,
 }
 
-impl Card {
-    fn new(line: &str) -> Self {
-        let (id_text, card_content) = line.split_once(':').unwrap();
-        let (winning_numbers_text, numbers_text) = card_content.split_once('|').unwrap();
-        let winning_numbers = split_numbers(winning_numbers_text, ' ');
-        let numbers = split_numbers(numbers_text, ' ');
+impl FromStr for Card {
+    type Err = String;
+
+    fn from_str(line: &str) -> Result<Self, Self::Err> {
+        let (id_text, card_content) = parse_header(line)?;
+        let (winning_numbers_text, numbers_text) = card_content
+            .split_once('|')
+            .ok_or(format!("invalid card line: {:?}", line))?;
+        let winning_numbers = try_split_numbers::<u8>(winning_numbers_text, ' ')?
+            .into_iter()
+            .collect();
+        let numbers = try_split_numbers(numbers_text, ' ')?;
         let id = id_text
-            .split_once(' ')
-            .unwrap()
-            .1
-            .trim()
-            .parse::<u8>()
-            .unwrap();
-        Self {
-            _id: id,
+            .strip_prefix("Card")
+            .and_then(|id| id.trim().parse::<u32>().ok())
+            .ok_or(format!("invalid card id: {:?}", id_text))?;
+        Ok(Self {
+            id,
             winning_numbers,
             numbers,
-        }
+        })
     }
+}
 
+impl Card {
     fn matches_count(&self) -> usize {
-        let mut count = 0;
-        for number in &self.numbers {
-            if self.winning_numbers.contains(number) {
-                count += 1;
-            }
+        self.numbers
+            .iter()
+            .filter(|number| self.winning_numbers.contains(**number))
+            .count()
+    }
+
+    /// Fails if the points don't fit, which can happen when numbers are repeated.
+    fn points(&self) -> Result<u64, String> {
+        match self.matches_count() {
+            0 => Ok(0),
+            matches => u32::try_from(matches - 1)
+                .ok()
+                .and_then(|shift| 1u64.checked_shl(shift))
+                .ok_or(format!("too many points for card {}", self.id)),
+        }
+    }
+}
+
+/// Parse the cards, checking that their ids go from 1 to the number of cards.
+fn parse_cards(input: &str) -> Result<Vec<Card>, String> {
+    let cards = input
+        .lines()
+        .map(|line| line.parse::<Card>())
+        .collect::<Result<Vec<Card>, String>>()?;
+    for (index, card) in cards.iter().enumerate() {
+        if card.id as usize != index + 1 {
+            return Err(format!(
+                "card ids are not sequential: expected {}, found {}",
+                index + 1,
+                card.id
+            ));
         }
-        count
     }
+    Ok(cards)
 }
 
-fn count_scratch_cards(input: &str) -> u32 {
-    let cards: Vec<Card> = input.lines().map(Card::new).collect();
-    do_count_scratch_cards(cards.as_slice(), cards.len(), 0)
+fn total_points(cards: &[Card]) -> Result<u64, String> {
+    cards.iter().try_fold(0u64, |total, card| {
+        total
+            .checked_add(card.points()?)
+            .ok_or("too many points".to_string())
+    })
 }
 
-fn do_count_scratch_cards(cards: &[Card], up_to_index: usize, count: u32) -> u32 {
-    let mut new_count = count;
-    for (index, card) in cards[..up_to_index].iter().enumerate() {
-        new_count += 1;
-        let matches = card.matches_count();
-        if matches > 0 {
-            let extra_cards = &cards[index + 1..];
-            new_count += do_count_scratch_cards(extra_cards, matches, count);
+/// The number of copies of each card won, originals included.
+///
+/// A card only gives copies of the cards after it, so copy counts are final once the forward
+/// pass reaches a card. Counts grow geometrically when cards have many matches, so this fails
+/// if they overflow.
+fn copy_counts(cards: &[Card]) -> Result<Vec<u64>, String> {
+    let mut copies = vec![1u64; cards.len()];
+    for (index, card) in cards.iter().enumerate() {
+        let won_cards = (index + 1)..(index + 1 + card.matches_count()).min(cards.len());
+        for won_card in won_cards {
+            copies[won_card] = copies[won_card]
+                .checked_add(copies[index])
+                .ok_or(format!("too many copies of card {}", won_card + 1))?;
         }
     }
-    new_count
+    Ok(copies)
+}
+
+fn count_scratch_cards(cards: &[Card]) -> Result<u64, String> {
+    copy_counts(cards)?
+        .into_iter()
+        .try_fold(0u64, |total, copies| total.checked_add(copies))
+        .ok_or("too many cards".to_string())
+}
+
+#[derive(Debug, PartialEq)]
+struct CardReport {
+    id: u32,
+    matches: usize,
+    points: u64,
+    copies: u64,
+}
+
+fn breakdown(cards: &[Card]) -> Result<Vec<CardReport>, String> {
+    cards
+        .iter()
+        .zip(copy_counts(cards)?)
+        .map(|(card, copies)| {
+            Ok(CardReport {
+                id: card.id,
+                matches: card.matches_count(),
+                points: card.points()?,
+                copies,
+            })
+        })
+        .collect()
+}
+
+fn format_table(reports: &[CardReport]) -> String {
+    let mut lines = vec![format!(
+        "{:>6} {:>8} {:>8} {:>10}",
+        "card", "matches", "points", "copies"
+    )];
+    lines.extend(reports.iter().map(|report| {
+        format!(
+            "{:>6} {:>8} {:>8} {:>10}",
+            report.id, report.matches, report.points, report.copies
+        )
+    }));
+    lines.join("\n")
+}
+
+fn format_json(reports: &[CardReport]) -> String {
+    let entries = reports
+        .iter()
+        .map(|report| {
+            format!(
+                "  {{\"id\": {}, \"matches\": {}, \"points\": {}, \"copies\": {}}}",
+                report.id, report.matches, report.points, report.copies
+            )
+        })
+        .collect::<Vec<_>>();
+    format!("[\n{}\n]", entries.join(",\n"))
+}
+
+fn solve(path: &str) {
+    let input = std::fs::read_to_string(path).unwrap();
+    let cards = parse_cards(&input).unwrap();
+    println!("Points: {}", total_points(&cards).unwrap());
+    println!("Total cards: {}", count_scratch_cards(&cards).unwrap());
 }
 
 impl Problem for Day4 {
     fn check(&self) {
-        let input = std::fs::read_to_string("src/day_4/example.txt").unwrap();
-        println!("Total cards: {}", count_scratch_cards(&input));
+        solve("src/day_4/example.txt");
     }
 
     fn solve(&self) {
-        let input = std::fs::read_to_string("src/day_4/input.txt").unwrap();
-        println!("Total cards: {}", count_scratch_cards(&input));
+        solve("src/day_4/input.txt");
+    }
+
+    fn analyze(&self) {
+        let input = std::fs::read_to_string(&self.input).unwrap();
+        let reports = breakdown(&parse_cards(&input).unwrap()).unwrap();
+        if self.json {
+            println!("{}", format_json(&reports));
+        } else {
+            println!("{}", format_table(&reports));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_number_set() {
+        let set = [0, 41, 64, 255].into_iter().collect::<NumberSet>();
+        assert!(set.contains(0));
+        assert!(set.contains(64));
+        assert!(set.contains(255));
+        assert!(!set.contains(63));
+        assert!(!set.contains(1));
+    }
+
+    #[test]
+    fn test_copy_counts() {
+        let cards = parse_cards(include_str!("example.txt")).unwrap();
+        assert_eq!(copy_counts(&cards), Ok(vec![1, 2, 4, 8, 14, 1]));
+        assert_eq!(count_scratch_cards(&cards), Ok(30));
+    }
+
+    #[test]
+    fn test_points() {
+        let cards = parse_cards(include_str!("example.txt")).unwrap();
+        assert_eq!(
+            cards.iter().map(Card::points).collect::<Vec<_>>(),
+            vec![Ok(8), Ok(2), Ok(2), Ok(1), Ok(0), Ok(0)]
+        );
+        assert_eq!(total_points(&cards), Ok(13));
+        // Repeated numbers can give more than 64 matches
+        let repeated = format!("Card 1: 7 | {}", vec!["7"; 64].join(" "));
+        assert_eq!(total_points(&parse_cards(&repeated).unwrap()), Ok(1 << 63));
+        let repeated = format!("Card 1: 7 | {}", vec!["7"; 65].join(" "));
+        assert_eq!(
+            total_points(&parse_cards(&repeated).unwrap()),
+            Err("too many points for card 1".to_string())
+        );
+    }
+
+    #[test]
+    fn test_sequential_ids() {
+        assert!(parse_cards("Card 1: 1 | 1\nCard 2: 1 | 1").is_ok());
+        assert_eq!(
+            parse_cards("Card 1: 1 | 1\nCard 3: 1 | 1").unwrap_err(),
+            "card ids are not sequential: expected 2, found 3"
+        );
+        assert!(parse_cards("Card 2: 1 | 1").is_err());
+    }
+
+    #[test]
+    fn test_card_ids() {
+        assert_eq!("Card   12: 1 | 1".parse::<Card>().unwrap().id, 12);
+        for line in [
+            "Card 1 2: 1 | 1",
+            "Card: 1 | 1",
+            "Game 1: 1 | 1",
+            "Card x: 1 | 1",
+        ] {
+            assert!(line.parse::<Card>().is_err());
+        }
+        assert_eq!(
+            "Card 1 2: 1 | 1".parse::<Card>().unwrap_err(),
+            "invalid card id: \"Card 1 2\""
+        );
+    }
+
+    #[test]
+    fn test_breakdown() {
+        let cards = parse_cards(include_str!("example.txt")).unwrap();
+        let reports = breakdown(&cards).unwrap();
+        assert_eq!(
+            reports[1],
+            CardReport {
+                id: 2,
+                matches: 2,
+                points: 2,
+                copies: 2
+            }
+        );
+        assert_eq!(
+            format_table(&reports[..1]),
+            "  card  matches   points     copies\n     1        4        8          1"
+        );
+        assert_eq!(
+            format_json(&reports[..2]),
+            "[\n  {\"id\": 1, \"matches\": 4, \"points\": 8, \"copies\": 1},\n  \
+             {\"id\": 2, \"matches\": 2, \"points\": 2, \"copies\": 2}\n]"
+        );
+    }
+
+    #[test]
+    fn test_long_cascade() {
+        // Every card wins the next one, except the last
+        let input = (1..=5000)
+            .map(|id| format!("Card {}: 1 | 1", id))
+            .collect::<Vec<_>>()
+            .join("\n");
+        let cards = parse_cards(&input).unwrap();
+        assert_eq!(cards[4999].id, 5000);
+        assert_eq!(count_scratch_cards(&cards), Ok(5000 * 5001 / 2));
+    }
+
+    #[test]
+    fn test_copies_overflow() {
+        // Every card wins the next 10 ones, so copy counts grow geometrically
+        let numbers = (1..=10)
+            .map(|number| number.to_string())
+            .collect::<Vec<_>>();
+        let card =
+            |id: usize| format!("Card {}: {} | {}", id, numbers.join(" "), numbers.join(" "));
+        let fitting = (1..=60).map(card).collect::<Vec<_>>().join("\n");
+        assert!(count_scratch_cards(&parse_cards(&fitting).unwrap()).is_ok());
+        let input = (1..=5000).map(card).collect::<Vec<_>>().join("\n");
+        let cards = parse_cards(&input).unwrap();
+        assert!(copy_counts(&cards)
+            .unwrap_err()
+            .starts_with("too many copies of card"));
+        assert!(count_scratch_cards(&cards).is_err());
     }
 }
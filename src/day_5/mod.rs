@@ -2,9 +2,22 @@
 
 use std::{collections::HashMap, ops::Range};
 
-use aoc_2023_rust_flupke::{split_numbers, Problem};
+use aoc_2023_rust_flupke::{parse_header_numbers, trace, try_split_numbers, Problem};
 
-pub struct Day5;
+use crate::common::interval::IntervalSet;
+
+pub struct Day5 {
+    /// Almanac file to analyze
+    input: String,
+}
+
+impl Day5 {
+    pub fn new(input: Option<String>) -> Self {
+        Self {
+            input: input.unwrap_or("src/day_5/input.txt".to_string()),
+        }
+    }
+}
 
 #[derive(Debug)]
 struct Mapper {
@@ -28,35 +41,39 @@ impl Mapper {
         }
     }
 
-    /// Cut a range with this mapper's range.
-    fn map_range(&self, range: &Range<u64>) -> Option<Vec<Range<u64>>> {
-        let mut ranges = Vec::new();
-        if (range.start >= self.source.end) || (range.end <= self.source.start) {
+    /// The values this mapper maps to.
+    fn destination_range(&self) -> Range<u64> {
+        self.destination..(self.destination + (self.source.end - self.source.start))
+    }
+
+    /// Cut a range with this mapper's range, mapping the part covered by the mapper.
+    fn map_range(&self, range: &Range<u64>) -> Option<RangeSplit> {
+        let start = range.start.max(self.source.start);
+        let end = range.end.min(self.source.end);
+        if start >= end {
             // Range out of mapper
             return None;
-        } else if self.source.contains(&range.start) && self.source.contains(&(range.end - 1)) {
-            // Mapper contains range
-            ranges.push(self.map(range.start).unwrap()..self.map(range.end - 1).unwrap() + 1);
-        } else if self.source.contains(&range.start) {
-            // Range start is in the mapper
-            ranges.push(self.map(range.start).unwrap()..self.map(self.source.end - 1).unwrap() + 1);
-            ranges.push(self.source.end..range.end);
-        } else if self.source.contains(&(range.end - 1)) {
-            // Range end is in the mapper
-            ranges.push(range.start..self.source.start);
-            ranges.push(self.map(self.source.start).unwrap()..self.map(range.end - 1).unwrap() + 1);
-        } else {
-            // Range contains the mapper
-            ranges.push(range.start..self.source.start);
-            ranges.push(
-                self.map(self.source.start).unwrap()..self.map(self.source.end - 1).unwrap() + 1,
-            );
-            ranges.push(self.source.end..range.end)
         }
-        Some(ranges)
+        let unmapped = [range.start..start, end..range.end]
+            .into_iter()
+            .filter(|piece| !piece.is_empty())
+            .collect();
+        Some(RangeSplit {
+            mapped: self.map(start).unwrap()..self.map(end - 1).unwrap() + 1,
+            unmapped,
+        })
     }
 }
 
+/// A range cut by a [`Mapper`].
+#[derive(Debug, PartialEq)]
+struct RangeSplit {
+    /// Image of the part of the range covered by the mapper
+    mapped: Range<u64>,
+    /// Parts of the range outside of the mapper, left as is
+    unmapped: Vec<Range<u64>>,
+}
+
 #[derive(Debug)]
 struct CorrespondenceMap {
     source: String,
@@ -73,41 +90,152 @@ impl CorrespondenceMap {
         }
     }
 
-    fn map_range(&self, range: &Range<u64>) -> Vec<Range<u64>> {
+    /// The image of a single value.
+    fn map(&self, value: u64) -> u64 {
         self.mappers
             .iter()
-            .find_map(|mapper| mapper.map_range(range))
-            .unwrap_or(vec![range.clone()])
+            .find_map(|mapper| mapper.map(value))
+            .unwrap_or(value)
+    }
+
+    /// The image of `range`. Each mapper is applied to the pieces left unmapped by the
+    /// previous ones, and the pieces no mapper covers are kept as is.
+    fn map_range(&self, range: &Range<u64>) -> IntervalSet {
+        let mut mapped = Vec::new();
+        let mut pending = vec![range.clone()];
+        for mapper in &self.mappers {
+            let mut unmapped = Vec::new();
+            for piece in pending {
+                match mapper.map_range(&piece) {
+                    Some(split) => {
+                        mapped.push(split.mapped);
+                        unmapped.extend(split.unmapped);
+                    }
+                    None => unmapped.push(piece),
+                }
+            }
+            pending = unmapped;
+            if pending.is_empty() {
+                break;
+            }
+        }
+        mapped.into_iter().chain(pending).collect()
+    }
+
+    /// The values whose image is in `ranges`.
+    fn unmap_ranges(&self, ranges: &IntervalSet) -> IntervalSet {
+        let mut covered = IntervalSet::new();
+        let mut preimage = IntervalSet::new();
+        for mapper in &self.mappers {
+            // Values already covered by a previous mapper aren't mapped by this one
+            let source = IntervalSet::from_ranges([mapper.source.clone()]).difference(&covered);
+            let destination = IntervalSet::from_ranges([mapper.destination_range()]);
+            let mapped_back = ranges
+                .intersection(&destination)
+                .remap([(mapper.destination_range(), mapper.source.start)]);
+            preimage = preimage.union(&mapped_back.intersection(&source));
+            covered.insert(mapper.source.clone());
+        }
+        preimage.union(&ranges.difference(&covered))
+    }
+
+    /// Non-overlapping mappers sorted by source, covering all the values below `u64::MAX`,
+    /// that map values the same way as this map.
+    fn pieces(&self) -> Vec<Mapper> {
+        let mut covered = IntervalSet::new();
+        let mut pieces = Vec::new();
+        for mapper in &self.mappers {
+            let source = IntervalSet::from_ranges([mapper.source.clone()]).difference(&covered);
+            pieces.extend(source.iter().map(|range| {
+                Mapper::new(
+                    range.start,
+                    mapper.map(range.start).unwrap(),
+                    range.end - range.start,
+                )
+            }));
+            covered.insert(mapper.source.clone());
+        }
+        let unmapped = IntervalSet::from_ranges([0..u64::MAX]).difference(&covered);
+        pieces.extend(
+            unmapped
+                .iter()
+                .map(|range| Mapper::new(range.start, range.start, range.end - range.start)),
+        );
+        pieces.sort_by_key(|piece| piece.source.start);
+        pieces
+    }
+
+    /// The map applying this map, then `next`.
+    fn then(&self, next: &CorrespondenceMap) -> CorrespondenceMap {
+        let next_pieces = next.pieces();
+        let mut composed = CorrespondenceMap::new(&self.source, &next.destination);
+        for piece in self.pieces() {
+            let image = piece.destination_range();
+            for next_piece in &next_pieces {
+                let start = image.start.max(next_piece.source.start);
+                let end = image.end.min(next_piece.source.end);
+                if start >= end {
+                    continue;
+                }
+                let source = piece.source.start + (start - image.start);
+                let destination = next_piece.map(start).unwrap();
+                if source != destination {
+                    composed
+                        .mappers
+                        .push(Mapper::new(source, destination, end - start));
+                }
+            }
+        }
+        composed.mappers.sort_by_key(|mapper| mapper.source.start);
+        composed
     }
 }
 
+/// How the numbers of the seeds line are read.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum SeedsMode {
+    /// Each number is a seed
+    Individual,
+    /// Numbers are pairs of (start, count) seeds ranges
+    Ranges,
+}
+
 #[derive(Debug)]
 struct Almanac {
-    seeds_ranges: Vec<Range<u64>>,
+    seeds: Vec<u64>,
     /// Correspondences maps, indexed by source
     correspondences: HashMap<String, CorrespondenceMap>,
 }
 
 impl Almanac {
     fn new(seeds: Vec<u64>) -> Self {
-        // convert the pairs of seeds numbers to ranges
-        let seeds_ranges = seeds
-            .chunks(2)
-            .map(|chunk| match chunk {
-                &[start, count] => start..(start + count),
-                _ => panic!("invalid seeds spec"),
-            })
-            .collect();
         Self {
-            seeds_ranges,
+            seeds,
             correspondences: HashMap::new(),
         }
     }
 
-    fn from_text(input: &str) -> Self {
+    fn seeds(&self, mode: SeedsMode) -> Result<IntervalSet, String> {
+        match mode {
+            SeedsMode::Individual => Ok(self.seeds.iter().map(|seed| *seed..seed + 1).collect()),
+            SeedsMode::Ranges => self
+                .seeds
+                .chunks(2)
+                .map(|chunk| match chunk {
+                    &[start, count] => Ok(start..(start + count)),
+                    _ => Err(format!(
+                        "invalid seeds ranges: odd numbers count ({})",
+                        self.seeds.len()
+                    )),
+                })
+                .collect(),
+        }
+    }
+
+    fn from_text(input: &str) -> Result<Self, String> {
         let mut lines = input.lines();
-        let seeds_line = lines.next().unwrap();
-        let seeds = split_numbers(seeds_line.split_once(':').unwrap().1, ' ');
+        let seeds_line = lines.next().ok_or("empty almanac")?;
+        let (_, seeds) = parse_header_numbers(seeds_line, ' ')?;
         let mut almanac = Self::new(seeds);
         let mut current_correspondence: Option<CorrespondenceMap> = None;
 
@@ -117,82 +245,376 @@ impl Almanac {
                 continue;
             }
             if line.ends_with(':') {
-                store_correspondence(&mut current_correspondence, &mut almanac.correspondences);
-                let (source, destination) = parse_correspondence_title(line);
+                store_correspondence(&mut current_correspondence, &mut almanac.correspondences)?;
+                let (source, destination) = parse_correspondence_title(line)?;
                 current_correspondence = Some(CorrespondenceMap::new(
                     source.as_str(),
                     destination.as_str(),
                 ));
             } else {
-                let numbers = split_numbers(line, ' ');
-                let destination = numbers[0];
-                let source = numbers[1];
-                let range = numbers[2];
-                if let Some(current_correspondence) = &mut current_correspondence {
-                    current_correspondence
-                        .mappers
-                        .push(Mapper::new(source, destination, range));
-                }
+                let numbers = try_split_numbers(line, ' ')?;
+                let &[destination, source, range] = numbers.as_slice() else {
+                    return Err(format!("invalid mapper line: {:?}", line));
+                };
+                current_correspondence
+                    .as_mut()
+                    .ok_or(format!("mapper line outside of a map: {:?}", line))?
+                    .mappers
+                    .push(Mapper::new(source, destination, range));
             }
         }
-        store_correspondence(&mut current_correspondence, &mut almanac.correspondences);
+        store_correspondence(&mut current_correspondence, &mut almanac.correspondences)?;
+        almanac.validate()?;
+
+        Ok(almanac)
+    }
 
-        almanac
+    /// Check that following the maps from any category never loops, and that seeds can be
+    /// mapped to locations.
+    fn validate(&self) -> Result<(), String> {
+        for source in self.correspondences.keys() {
+            let mut visited = vec![source.as_str()];
+            let mut current = source.as_str();
+            while let Some(correspondence_map) = self.correspondences.get(current) {
+                current = &correspondence_map.destination;
+                if visited.contains(&current) {
+                    visited.push(current);
+                    return Err(format!("cycle in category maps: {}", visited.join(" -> ")));
+                }
+                visited.push(current);
+            }
+        }
+        self.chain("seed", "location")?;
+        Ok(())
     }
 
-    fn map_seeds(&self, source: &str, destination: &str) -> Vec<Range<u64>> {
+    /// The maps to go through to convert `source` categories to `destination` ones.
+    fn chain(&self, source: &str, destination: &str) -> Result<Vec<&CorrespondenceMap>, String> {
+        let mut chain = Vec::new();
         let mut current_source = source;
-        let mut current_ranges = self.seeds_ranges.clone();
         while current_source != destination {
-            let correspondence_map = &self.correspondences[current_source];
-            println!(
-                "mapping {} to {}",
-                current_source, correspondence_map.destination
-            );
+            let correspondence_map = self.correspondences.get(current_source).ok_or(format!(
+                "no path from {:?} to {:?}: no map from {:?}",
+                source, destination, current_source
+            ))?;
+            if chain.len() > self.correspondences.len() {
+                return Err(format!(
+                    "no path from {:?} to {:?}: cycle in category maps",
+                    source, destination
+                ));
+            }
+            chain.push(correspondence_map);
+            current_source = &correspondence_map.destination;
+        }
+        Ok(chain)
+    }
+
+    fn map_ranges(
+        &self,
+        ranges: IntervalSet,
+        source: &str,
+        destination: &str,
+    ) -> Result<IntervalSet, String> {
+        let mut current_ranges = ranges;
+        trace!(
+            "day 5",
+            category = source,
+            ranges = current_ranges.ranges().len(),
+            values = current_ranges.len()
+        );
+        for correspondence_map in self.chain(source, destination)? {
             current_ranges = current_ranges
                 .iter()
-                .flat_map(|range| correspondence_map.map_range(range))
-                .collect();
-            current_source = &correspondence_map.destination;
+                .fold(IntervalSet::new(), |image, range| {
+                    image.union(&correspondence_map.map_range(range))
+                });
+            trace!(
+                "day 5",
+                category = correspondence_map.destination,
+                ranges = current_ranges.ranges().len(),
+                values = current_ranges.len()
+            );
         }
-        current_ranges
+        Ok(current_ranges)
+    }
+
+    fn map_seeds(&self, mode: SeedsMode, destination: &str) -> Result<IntervalSet, String> {
+        self.map_ranges(self.seeds(mode)?, "seed", destination)
     }
 
-    fn find_lowest_location(&self) -> u64 {
-        self.map_seeds("seed", "location")
+    fn find_lowest_location(&self, mode: SeedsMode) -> Result<u64, String> {
+        self.map_seeds(mode, "location")?
+            .min()
+            .ok_or("no seeds".to_string())
+    }
+
+    /// The `source` values that map to `ranges` of `destination` values.
+    fn unmap_ranges(
+        &self,
+        ranges: IntervalSet,
+        destination: &str,
+        source: &str,
+    ) -> Result<IntervalSet, String> {
+        let mut current_ranges = ranges;
+        for correspondence_map in self.chain(source, destination)?.iter().rev() {
+            current_ranges = correspondence_map.unmap_ranges(&current_ranges);
+        }
+        Ok(current_ranges)
+    }
+
+    /// The seeds landing in the `locations` window.
+    fn seeds_reaching(
+        &self,
+        mode: SeedsMode,
+        locations: Range<u64>,
+    ) -> Result<IntervalSet, String> {
+        Ok(self
+            .unmap_ranges(IntervalSet::from_ranges([locations]), "location", "seed")?
+            .intersection(&self.seeds(mode)?))
+    }
+
+    /// A single map going from `source` to `destination` values.
+    fn compose(&self, source: &str, destination: &str) -> Result<CorrespondenceMap, String> {
+        Ok(self
+            .chain(source, destination)?
+            .into_iter()
+            .fold(CorrespondenceMap::new(source, source), |composed, next| {
+                composed.then(next)
+            }))
+    }
+
+    /// Same as [`Almanac::find_lowest_location`], using the composed seed to location map.
+    fn find_lowest_location_composed(&self, mode: SeedsMode) -> Result<u64, String> {
+        let seeds = self.seeds(mode)?;
+        let composed = self.compose("seed", "location")?;
+        composed
+            .pieces()
             .iter()
-            .map(|range| range.start)
+            .filter_map(|piece| {
+                let reached = seeds.intersection(&IntervalSet::from_ranges([piece.source.clone()]));
+                reached.min().map(|seed| composed.map(seed))
+            })
             .min()
-            .unwrap()
+            .ok_or("no seeds".to_string())
     }
 }
 
 fn store_correspondence(
     current_correspondence: &mut Option<CorrespondenceMap>,
     correspondences: &mut HashMap<String, CorrespondenceMap>,
-) {
+) -> Result<(), String> {
     if let Some(correspondence) = current_correspondence.take() {
+        if correspondences.contains_key(&correspondence.source) {
+            return Err(format!(
+                "several maps from category {:?}",
+                correspondence.source
+            ));
+        }
         correspondences.insert(correspondence.source.clone(), correspondence);
     }
+    Ok(())
 }
 
-fn parse_correspondence_title(line: &str) -> (String, String) {
+fn parse_correspondence_title(line: &str) -> Result<(String, String), String> {
     let line = line.trim_end_matches(" map:");
-    let (source, destination) = line.split_once("-to-").unwrap();
-    (source.to_string(), destination.to_string())
+    let (source, destination) = line
+        .split_once("-to-")
+        .ok_or(format!("invalid map title: {:?}", line))?;
+    Ok((source.to_string(), destination.to_string()))
+}
+
+fn solve(path: &str) {
+    let almanac = Almanac::from_text(&std::fs::read_to_string(path).unwrap()).unwrap();
+    println!(
+        "min location (individual seeds): {}",
+        almanac.find_lowest_location(SeedsMode::Individual).unwrap()
+    );
+    println!(
+        "min location: {}",
+        almanac.find_lowest_location(SeedsMode::Ranges).unwrap()
+    );
 }
 
 impl Problem for Day5 {
     fn check(&self) {
-        let almanac =
-            Almanac::from_text(&std::fs::read_to_string("src/day_5/example.txt").unwrap());
-        println!("min location: {}", almanac.find_lowest_location());
+        solve("src/day_5/example.txt");
     }
 
     fn solve(&self) {
-        let almanac = Almanac::from_text(&std::fs::read_to_string("src/day_5/input.txt").unwrap());
-        dbg!(&almanac);
-        println!("min location: {}", almanac.find_lowest_location());
+        solve("src/day_5/input.txt");
+    }
+
+    fn analyze(&self) {
+        let almanac = Almanac::from_text(&std::fs::read_to_string(&self.input).unwrap()).unwrap();
+        let composed = almanac.compose("seed", "location").unwrap();
+        println!("seed to location: {} pieces", composed.pieces().len());
+        for mode in [SeedsMode::Individual, SeedsMode::Ranges] {
+            let location = almanac.find_lowest_location_composed(mode).unwrap();
+            let seeds = almanac
+                .seeds_reaching(mode, location..location + 1)
+                .unwrap();
+            println!(
+                "{:?} seeds: lowest location {} reached from seeds {:?}",
+                mode,
+                location,
+                seeds.ranges()
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod test_almanac {
+    use super::*;
+
+    #[test]
+    fn test_seeds_modes() {
+        let almanac = Almanac::new(vec![79, 14, 55, 13]);
+        assert_eq!(
+            almanac.seeds(SeedsMode::Individual),
+            Ok(IntervalSet::from_ranges([13..15, 55..56, 79..80]))
+        );
+        assert_eq!(
+            almanac.seeds(SeedsMode::Ranges),
+            Ok(IntervalSet::from_ranges([55..68, 79..93]))
+        );
+        assert!(Almanac::new(vec![1, 2, 3])
+            .seeds(SeedsMode::Ranges)
+            .is_err());
+    }
+
+    #[test]
+    fn test_find_lowest_location() {
+        let almanac = Almanac::from_text(include_str!("example.txt")).unwrap();
+        assert_eq!(
+            almanac.map_seeds(SeedsMode::Individual, "soil"),
+            Ok(IntervalSet::from_ranges([13..15, 57..58, 81..82]))
+        );
+        assert_eq!(almanac.find_lowest_location(SeedsMode::Individual), Ok(35));
+        assert_eq!(almanac.find_lowest_location(SeedsMode::Ranges), Ok(46));
+    }
+
+    #[test]
+    fn test_map_seeds_matches_map() {
+        let almanac = Almanac::from_text(include_str!("example.txt")).unwrap();
+        let chain = almanac.chain("seed", "location").unwrap();
+        let mut rng = fastrand::Rng::with_seed(5);
+        for _ in 0..200 {
+            let seeds = (0..rng.usize(1..5))
+                .map(|_| {
+                    let start = rng.u64(0..100);
+                    start..start + rng.u64(1..20)
+                })
+                .collect::<IntervalSet>();
+            let expected = seeds
+                .iter()
+                .flat_map(|range| range.clone())
+                .map(|seed| {
+                    let location = chain.iter().fold(seed, |value, correspondence_map| {
+                        correspondence_map.map(value)
+                    });
+                    location..location + 1
+                })
+                .collect::<IntervalSet>();
+            assert_eq!(almanac.map_ranges(seeds, "seed", "location"), Ok(expected));
+        }
+    }
+
+    #[test]
+    fn test_unmap() {
+        let almanac = Almanac::from_text(include_str!("example.txt")).unwrap();
+        // Seeds 79, 14, 55 and 13 land in locations 82, 43, 86 and 35
+        assert_eq!(
+            almanac.seeds_reaching(SeedsMode::Individual, 0..83),
+            Ok(IntervalSet::from_ranges([13..15, 79..80]))
+        );
+        assert_eq!(
+            almanac.seeds_reaching(SeedsMode::Individual, 83..1000),
+            Ok(IntervalSet::from_ranges([55..56]))
+        );
+        // Seed 82 is the only one reaching location 46
+        assert_eq!(
+            almanac.seeds_reaching(SeedsMode::Ranges, 46..47),
+            Ok(IntervalSet::from_ranges([82..83]))
+        );
+    }
+
+    #[test]
+    fn test_unmap_matches_map() {
+        let almanac = Almanac::from_text(include_str!("example.txt")).unwrap();
+        let composed = almanac.compose("seed", "location").unwrap();
+        let mut rng = fastrand::Rng::with_seed(41);
+        for _ in 0..200 {
+            let start = rng.u64(0..120);
+            let locations = start..start + rng.u64(1..20);
+            let expected = (0..200)
+                .filter(|seed| locations.contains(&composed.map(*seed)))
+                .map(|seed| seed..seed + 1)
+                .collect::<IntervalSet>();
+            let seeds = almanac
+                .unmap_ranges(IntervalSet::from_ranges([locations]), "location", "seed")
+                .unwrap()
+                .intersection(&IntervalSet::from_ranges([0..200]));
+            assert_eq!(seeds, expected);
+        }
+    }
+
+    #[test]
+    fn test_compose() {
+        let almanac = Almanac::from_text(include_str!("example.txt")).unwrap();
+        let chain = almanac.chain("seed", "location").unwrap();
+        let composed = almanac.compose("seed", "location").unwrap();
+        for seed in 0..200 {
+            let location = chain.iter().fold(seed, |value, correspondence_map| {
+                correspondence_map.map(value)
+            });
+            assert_eq!(composed.map(seed), location);
+        }
+        for mode in [SeedsMode::Individual, SeedsMode::Ranges] {
+            assert_eq!(
+                almanac.find_lowest_location_composed(mode),
+                almanac.find_lowest_location(mode)
+            );
+        }
+    }
+
+    #[test]
+    fn test_any_categories_pair() {
+        let almanac = Almanac::from_text(include_str!("example.txt")).unwrap();
+        let soils = IntervalSet::from_ranges([14..15]);
+        // soil 14 -> fertilizer 53 -> water 49
+        assert_eq!(
+            almanac.map_ranges(soils, "soil", "water"),
+            Ok(IntervalSet::from_ranges([49..50]))
+        );
+        assert_eq!(
+            almanac.chain("seed", "seed").map(|chain| chain.len()),
+            Ok(0)
+        );
+        assert_eq!(
+            almanac.chain("location", "seed").unwrap_err(),
+            "no path from \"location\" to \"seed\": no map from \"location\""
+        );
+    }
+
+    #[test]
+    fn test_validation() {
+        let cycle = "seeds: 1\n\na-to-b map:\n1 2 3\n\nb-to-a map:\n1 2 3";
+        assert!(Almanac::from_text(cycle)
+            .unwrap_err()
+            .starts_with("cycle in category maps"));
+        let duplicate = "seeds: 1\n\na-to-b map:\n1 2 3\n\na-to-c map:\n1 2 3";
+        assert_eq!(
+            Almanac::from_text(duplicate).unwrap_err(),
+            "several maps from category \"a\""
+        );
+        let missing_link = "seeds: 1\n\nseed-to-soil map:\n1 2 3\n\nwater-to-location map:\n1 2 3";
+        assert_eq!(
+            Almanac::from_text(missing_link).unwrap_err(),
+            "no path from \"seed\" to \"location\": no map from \"soil\""
+        );
+        assert!(Almanac::from_text("seeds: 1\n\na-to-b map:\n1 2").is_err());
+        assert!(Almanac::from_text("seeds: x").is_err());
     }
 }
 
@@ -209,29 +631,36 @@ mod test_mapper {
         assert_eq!(mapper.map(11), None);
     }
 
+    fn split(mapped: Range<u64>, unmapped: &[Range<u64>]) -> Option<RangeSplit> {
+        Some(RangeSplit {
+            mapped,
+            unmapped: unmapped.to_vec(),
+        })
+    }
+
     #[test]
     fn test_map_range_includes_range() {
         let mapper = Mapper::new(0, 10, 10);
-        assert_eq!(mapper.map_range(&(0..10)), Some(vec![10..20]));
-        assert_eq!(mapper.map_range(&(1..9)), Some(vec![11..19]));
+        assert_eq!(mapper.map_range(&(0..10)), split(10..20, &[]));
+        assert_eq!(mapper.map_range(&(1..9)), split(11..19, &[]));
     }
 
     #[test]
     fn test_map_range_includes_start() {
         let mapper = Mapper::new(0, 10, 10);
-        assert_eq!(mapper.map_range(&(5..15)), Some(vec![15..20, 10..15]));
+        assert_eq!(mapper.map_range(&(5..15)), split(15..20, &[10..15]));
     }
 
     #[test]
     fn test_map_range_includes_end() {
         let mapper = Mapper::new(5, 15, 10);
-        assert_eq!(mapper.map_range(&(0..10)), Some(vec![0..5, 15..20]));
+        assert_eq!(mapper.map_range(&(0..10)), split(15..20, &[0..5]));
     }
 
     #[test]
     fn test_map_range_included_in_range() {
         let mapper = Mapper::new(5, 15, 10);
-        assert_eq!(mapper.map_range(&(0..20)), Some(vec![0..5, 15..25, 15..20]));
+        assert_eq!(mapper.map_range(&(0..20)), split(15..25, &[0..5, 15..20]));
     }
 
     #[test]
@@ -244,15 +673,67 @@ mod test_mapper {
     #[test]
     fn test_map_range_conserves_length() {
         let mapper = Mapper::new(5, 15, 10);
-        let ranges_length = |ranges: &[Range<u64>]| {
-            ranges
-                .iter()
-                .map(|range| range.clone().count())
-                .sum::<usize>()
+        let split_length = |split: RangeSplit| {
+            split.mapped.count()
+                + split
+                    .unmapped
+                    .into_iter()
+                    .map(|range| range.count())
+                    .sum::<usize>()
         };
-        assert_eq!(ranges_length(&mapper.map_range(&(5..15)).unwrap()), 10);
-        assert_eq!(ranges_length(&mapper.map_range(&(0..10)).unwrap()), 10);
-        assert_eq!(ranges_length(&mapper.map_range(&(10..20)).unwrap()), 10);
-        assert_eq!(ranges_length(&mapper.map_range(&(0..20)).unwrap()), 20);
+        assert_eq!(split_length(mapper.map_range(&(5..15)).unwrap()), 10);
+        assert_eq!(split_length(mapper.map_range(&(0..10)).unwrap()), 10);
+        assert_eq!(split_length(mapper.map_range(&(10..20)).unwrap()), 10);
+        assert_eq!(split_length(mapper.map_range(&(0..20)).unwrap()), 20);
+    }
+
+    #[test]
+    fn test_leftovers_use_other_mappers() {
+        let mut correspondence_map = CorrespondenceMap::new("a", "b");
+        correspondence_map.mappers.push(Mapper::new(0, 100, 10));
+        correspondence_map.mappers.push(Mapper::new(10, 200, 10));
+        assert_eq!(
+            correspondence_map.map_range(&(5..25)),
+            IntervalSet::from_ranges([20..25, 105..110, 200..210])
+        );
+    }
+
+    /// A map of non-overlapping mappers with random ranges, as found in almanacs.
+    fn random_correspondence_map(rng: &mut fastrand::Rng) -> CorrespondenceMap {
+        let mut correspondence_map = CorrespondenceMap::new("a", "b");
+        let mut source = 0;
+        for _ in 0..rng.usize(0..6) {
+            source += rng.u64(0..20);
+            let range = rng.u64(1..20);
+            correspondence_map
+                .mappers
+                .push(Mapper::new(source, rng.u64(0..200), range));
+            source += range;
+        }
+        rng.shuffle(&mut correspondence_map.mappers);
+        correspondence_map
+    }
+
+    #[test]
+    fn test_map_range_matches_map() {
+        let mut rng = fastrand::Rng::with_seed(5);
+        for _ in 0..500 {
+            let correspondence_map = random_correspondence_map(&mut rng);
+            let start = rng.u64(0..150);
+            let range = start..start + rng.u64(1..50);
+            assert_eq!(
+                correspondence_map.map_range(&range),
+                range
+                    .clone()
+                    .map(|value| {
+                        let mapped = correspondence_map.map(value);
+                        mapped..mapped + 1
+                    })
+                    .collect::<IntervalSet>(),
+                "{:?} through {:?}",
+                range,
+                correspondence_map
+            );
+        }
     }
 }
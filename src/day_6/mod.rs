@@ -1,4 +1,4 @@
-use aoc_2023_rust_flupke::{split_numbers, Problem};
+use aoc_2023_rust_flupke::{parse_header, Problem};
 
 pub struct Day6;
 
@@ -11,76 +11,222 @@ struct Race {
 }
 
 impl Race {
-    fn distance_for_press(&self, press_duration: Unit) -> Unit {
-        let remaining = self.duration - press_duration;
-        if remaining > 0 {
-            press_duration * remaining
-        } else {
-            0
-        }
+    /// Whether pressing for `press_duration` beats the record distance.
+    fn wins(&self, press_duration: Unit) -> bool {
+        press_duration <= self.duration
+            && press_duration as u128 * (self.duration - press_duration) as u128
+                > self.distance as u128
     }
 
+    /// Count the press durations `t` beating the record, i.e. with `t * (T - t) > D`.
+    ///
+    /// These are the integers strictly between the roots of `t² - T t + D`, found from the
+    /// integer square root of the discriminant then adjusted to the exact boundary.
     fn count_wins(&self) -> Unit {
-        let mut total = 0;
-        for press_duration in 1..self.duration {
-            if self.distance_for_press(press_duration) > self.distance {
-                total += 1;
-            }
+        let duration = self.duration as u128;
+        let discriminant = match (duration * duration).checked_sub(4 * self.distance as u128) {
+            Some(discriminant) if discriminant > 0 => discriminant,
+            _ => return 0,
+        };
+        // The lowest winning press is close to the lowest root (T - √Δ) / 2
+        let mut lowest = ((duration - discriminant.isqrt()) / 2) as Unit;
+        while lowest > 0 && self.wins(lowest - 1) {
+            lowest -= 1;
+        }
+        while lowest <= self.duration / 2 && !self.wins(lowest) {
+            lowest += 1;
+        }
+        if lowest > self.duration / 2 {
+            return 0;
         }
-        total
+        // Wins are symmetric around T / 2
+        self.duration - 2 * lowest + 1
+    }
+}
+
+/// How the numbers of a line are read.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Kerning {
+    /// Each column is a number
+    Separate,
+    /// Spaces are ignored, the whole line is a single number
+    Kerned,
+}
+
+/// Parse the columns of a `key: numbers` line, checking its key.
+fn parse_columns<'a>(line: Option<&'a str>, key: &str) -> Result<Vec<&'a str>, String> {
+    let line = line.ok_or(format!("missing {} line", key))?;
+    let (line_key, values) = parse_header(line)?;
+    if line_key != key {
+        return Err(format!("expected {} line, found {:?}", key, line));
+    }
+    Ok(values.split_whitespace().collect())
+}
+
+/// Read the numbers of a line from its columns.
+fn parse_numbers(columns: &[&str], kerning: Kerning) -> Result<Vec<Unit>, String> {
+    let parse = |number: &str| {
+        number
+            .parse::<Unit>()
+            .map_err(|err| format!("invalid number {:?}: {}", number, err))
+    };
+    match kerning {
+        Kerning::Separate => columns.iter().map(|column| parse(column)).collect(),
+        Kerning::Kerned => Ok(vec![parse(&columns.concat())?]),
     }
 }
 
-fn parse_races(text: &str) -> Vec<Race> {
+fn parse_races(text: &str, kerning: Kerning) -> Result<Vec<Race>, String> {
     let mut lines = text.lines();
-    let durations = split_numbers::<Unit>(
-        lines
-            .next()
-            .expect("durations line")
-            .split_once(' ')
-            .expect("durations list")
-            .1,
-        ' ',
-    );
-    let distances = split_numbers::<Unit>(
-        lines
-            .next()
-            .expect("distances line")
-            .split_once(' ')
-            .expect("distances list")
-            .1,
-        ' ',
-    );
-    durations
-        .iter()
-        .zip(distances)
-        .map(|(duration, distance)| Race {
-            duration: *duration,
-            distance,
-        })
-        .collect()
+    let durations = parse_columns(lines.next(), "Time")?;
+    let distances = parse_columns(lines.next(), "Distance")?;
+    if durations.len() != distances.len() {
+        return Err(format!(
+            "{} durations for {} distances",
+            durations.len(),
+            distances.len()
+        ));
+    }
+    Ok(parse_numbers(&durations, kerning)?
+        .into_iter()
+        .zip(parse_numbers(&distances, kerning)?)
+        .map(|(duration, distance)| Race { duration, distance })
+        .collect())
 }
 
-fn multiply_total_wins(input_text: &str) -> Unit {
-    parse_races(input_text)
+fn multiply_total_wins(input_text: &str, kerning: Kerning) -> Unit {
+    parse_races(input_text, kerning)
+        .unwrap()
         .iter()
         .map(|race| race.count_wins())
-        .reduce(|a, b| a * b)
-        .unwrap()
+        .product()
+}
+
+fn solve(input_text: &str) {
+    println!(
+        "Wins multiplied: {}",
+        multiply_total_wins(input_text, Kerning::Separate)
+    );
+    println!(
+        "Wins of the kerned race: {}",
+        multiply_total_wins(input_text, Kerning::Kerned)
+    );
 }
 
 impl Problem for Day6 {
     fn check(&self) {
-        println!(
-            "Wins multiplied: {}",
-            multiply_total_wins(include_str!("example.txt"))
-        );
+        solve(include_str!("example.txt"));
     }
 
     fn solve(&self) {
-        println!(
-            "Wins multiplied: {}",
-            multiply_total_wins(include_str!("input.txt"))
+        solve(include_str!("input.txt"));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn count_wins_brute_force(race: &Race) -> Unit {
+        (0..=race.duration)
+            .filter(|press_duration| {
+                press_duration * (race.duration - press_duration) > race.distance
+            })
+            .count() as Unit
+    }
+
+    #[test]
+    fn test_example() {
+        let races =
+            [(7, 9), (15, 40), (30, 200)].map(|(duration, distance)| Race { duration, distance });
+        assert_eq!(races.map(|race| race.count_wins()), [4, 8, 9]);
+        assert_eq!(
+            multiply_total_wins(include_str!("example.txt"), Kerning::Kerned),
+            71503
         );
+        let race = Race {
+            duration: 71530,
+            distance: 940200,
+        };
+        assert_eq!(race.count_wins(), 71503);
+    }
+
+    #[test]
+    fn test_parse_races() {
+        let text = "Time:      7  15   30\nDistance:  9  40  200";
+        let races = parse_races(text, Kerning::Separate).unwrap();
+        assert_eq!(
+            races
+                .iter()
+                .map(|race| (race.duration, race.distance))
+                .collect::<Vec<_>>(),
+            vec![(7, 9), (15, 40), (30, 200)]
+        );
+        assert_eq!(multiply_total_wins(text, Kerning::Separate), 288);
+        let kerned = parse_races(text, Kerning::Kerned).unwrap();
+        assert_eq!((kerned[0].duration, kerned[0].distance), (71530, 940200));
+        assert_eq!(multiply_total_wins(text, Kerning::Kerned), 71503);
+    }
+
+    #[test]
+    fn test_parse_races_errors() {
+        for kerning in [Kerning::Separate, Kerning::Kerned] {
+            assert_eq!(
+                parse_races("Time: 7 15\nDistance: 9", kerning).unwrap_err(),
+                "2 durations for 1 distances"
+            );
+            assert!(parse_races("Time: 7 x\nDistance: 9 40", kerning).is_err());
+            assert!(parse_races("Distance: 9\nTime: 7", kerning).is_err());
+            assert!(parse_races("Time: 7", kerning).is_err());
+        }
+        assert!(parse_races(
+            "Time: 99999999999 99999999999\nDistance: 1 1",
+            Kerning::Kerned
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_boundaries() {
+        // Pressing 5 exactly ties the record
+        let tie = Race {
+            duration: 10,
+            distance: 25,
+        };
+        assert_eq!(tie.count_wins(), 0);
+        let perfect_square = Race {
+            duration: 10,
+            distance: 24,
+        };
+        assert_eq!(perfect_square.count_wins(), 1);
+        let too_far = Race {
+            duration: 10,
+            distance: 100,
+        };
+        assert_eq!(too_far.count_wins(), 0);
+        let huge = Race {
+            duration: u64::MAX,
+            distance: 0,
+        };
+        assert_eq!(huge.count_wins(), u64::MAX - 1);
+    }
+
+    #[test]
+    fn test_against_brute_force() {
+        let mut rng = fastrand::Rng::with_seed(6);
+        for _ in 0..2000 {
+            let duration = rng.u64(0..200);
+            let best = (duration / 2) * (duration - duration / 2);
+            let race = Race {
+                duration,
+                distance: rng.u64(0..=best + 1),
+            };
+            assert_eq!(
+                race.count_wins(),
+                count_wins_brute_force(&race),
+                "{:?}",
+                race
+            );
+        }
     }
 }
@@ -1,132 +1,102 @@
-use std::{str::FromStr, usize};
+use std::str::FromStr;
 
 use aoc_2023_rust_flupke::Problem;
 use itertools::Itertools;
 
 pub struct Day7;
 
-const CARDS: &str = "J23456789TQKA";
+/// How cards are ranked.
+#[derive(Debug, Clone, Copy)]
+struct Rules {
+    /// The cards, from weakest to strongest
+    order: &'static str,
+    /// Whether J cards are jokers, acting like whatever card makes the best hand
+    jokers_wild: bool,
+}
+
+impl Rules {
+    /// J is a Jack, between T and Q.
+    const STANDARD: Self = Self {
+        order: "23456789TJQKA",
+        jokers_wild: false,
+    };
 
-fn card_value(card: char) -> Result<usize, String> {
-    CARDS.find(card).ok_or(format!("invalid card: {:?}", card))
+    /// J is a joker, the weakest card on its own.
+    const JOKERS: Self = Self {
+        order: "J23456789TQKA",
+        jokers_wild: true,
+    };
+
+    fn card_value(&self, card: char) -> Result<usize, String> {
+        self.order
+            .find(card)
+            .ok_or(format!("invalid card: {:?}", card))
+    }
 }
 
-type HandValue = (HandType, usize, usize, usize, usize, usize);
+/// The type of a hand, compared by type, then card by card.
+type HandValue = (HandType, Vec<usize>);
 
 struct Hand {
-    cards: Vec<usize>,
+    cards: Vec<char>,
 }
 
-#[derive(Debug, PartialOrd, Eq, Ord, PartialEq, Clone, Copy)]
-enum HandType {
-    HighCard = 0,
-    Pair = 1,
-    TwoPairs = 2,
-    Triple = 3,
-    FullHouse = 4,
-    Quad = 5,
-    Quint = 6,
-}
+/// The counts of each distinct card in a hand, in decreasing order, e.g. `[3, 2]` for a full
+/// house. Comparing signatures lexicographically ranks five-card hands as usual (high card
+/// `[1, 1, 1, 1, 1]` < pair `[2, 1, 1, 1]` < two pairs `[2, 2, 1]` < ... < five of a kind
+/// `[5]`), and extends to any number of cards.
+#[derive(Debug, PartialOrd, Eq, Ord, PartialEq, Clone)]
+struct HandType(Vec<usize>);
 
-impl Hand {
-    fn value(&self) -> HandValue {
-        (
-            self.best_hand_type(),
-            self.cards[0],
-            self.cards[1],
-            self.cards[2],
-            self.cards[3],
-            self.cards[4],
-        )
-    }
-
-    fn best_hand_type(&self) -> HandType {
-        let mut sorted_cards = self.cards.clone();
-        sorted_cards.sort_by(|a, b| b.cmp(a));
-        let jokers_chain = find_jokers_chain(&sorted_cards);
-        if let Some((start, length)) = jokers_chain {
-            (0..length)
-                .map(|_| 1..CARDS.len())
-                .multi_cartesian_product()
-                .map(|joker_cards| {
-                    let mut cards = sorted_cards.clone();
-                    for (index, card) in joker_cards.iter().enumerate() {
-                        cards[start + index] = *card;
-                    }
-                    cards.sort_by(|a, b| b.cmp(a));
-                    hand_type(&cards)
-                })
-                .max()
-                .unwrap()
-        } else {
-            hand_type(&sorted_cards)
-        }
+impl HandType {
+    fn from_cards(cards: &[usize]) -> Self {
+        let mut counts = cards.iter().counts().into_values().collect::<Vec<_>>();
+        counts.sort_by(|a, b| b.cmp(a));
+        Self(counts)
     }
 }
 
-fn hand_type(sorted_cards: &Vec<usize>) -> HandType {
-    let mut num_pairs = 0;
-    let mut num_triples = 0;
-    let mut num_quads = 0;
-    let mut num_quints = 0;
-    let mut prev_card = usize::MAX;
-    let mut chain_length = 1;
-
-    let mut update_counts = |chain_length: &usize| match chain_length {
-        1 => {}
-        2 => num_pairs += 1,
-        3 => num_triples += 1,
-        4 => num_quads += 1,
-        5 => num_quints += 1,
-        _ => panic!("too many cards!"),
-    };
-
-    for card in sorted_cards {
-        if *card == prev_card {
-            chain_length += 1;
-        } else {
-            update_counts(&chain_length);
-            chain_length = 1;
-        }
-        prev_card = *card
+impl Hand {
+    /// The values of the cards, or an error if a card is not part of the rules.
+    fn card_values(&self, rules: &Rules) -> Result<Vec<usize>, String> {
+        self.cards
+            .iter()
+            .map(|card| rules.card_value(*card))
+            .collect()
     }
-    update_counts(&chain_length);
 
-    match (num_quints, num_quads, num_triples, num_pairs) {
-        (1, _, _, _) => HandType::Quint,
-        (_, 1, _, _) => HandType::Quad,
-        (_, _, 1, 1) => HandType::FullHouse,
-        (_, _, 1, _) => HandType::Triple,
-        (_, _, _, 2) => HandType::TwoPairs,
-        (_, _, _, 1) => HandType::Pair,
-        _ => HandType::HighCard,
+    fn value(&self, rules: &Rules) -> Result<HandValue, String> {
+        Ok((self.best_hand_type(rules)?, self.card_values(rules)?))
     }
-}
 
-fn find_jokers_chain(sorted_cards: &[usize]) -> Option<(usize, usize)> {
-    let mut chain_length = 0;
-    let mut chain_start = None;
-    for (index, card) in sorted_cards.iter().enumerate() {
-        if *card == 0 {
-            if chain_start.is_none() {
-                chain_start = Some(index);
-            }
-            chain_length += 1;
+    /// The best hand type this hand can make: jokers, if wild, are best used as copies of the
+    /// most frequent other card.
+    fn best_hand_type(&self, rules: &Rules) -> Result<HandType, String> {
+        let cards = self.card_values(rules)?;
+        if !rules.jokers_wild {
+            return Ok(HandType::from_cards(&cards));
         }
+        let joker = rules.card_value('J')?;
+        let (jokers, others): (Vec<usize>, Vec<usize>) =
+            cards.into_iter().partition(|card| *card == joker);
+        let HandType(mut counts) = HandType::from_cards(&others);
+        match counts.first_mut() {
+            Some(most_frequent) => *most_frequent += jokers.len(),
+            None => counts.push(jokers.len()),
+        }
+        Ok(HandType(counts))
     }
-    chain_start.map(|start| (start, chain_length))
 }
 
 impl FromStr for Hand {
     type Err = String;
 
+    /// Cards are only checked against the [`Rules`] when valuing the hand, so the same hand can
+    /// be played with any set of rules.
     fn from_str(input: &str) -> Result<Self, Self::Err> {
-        let cards = input
-            .chars()
-            .map(card_value)
-            .collect::<Result<Vec<usize>, String>>()?;
-        if cards.len() != 5 {
-            Err(format!("hand is not 5 cards: {:?}", input))
+        let cards = input.chars().collect::<Vec<_>>();
+        if cards.is_empty() {
+            Err("empty hand".to_string())
         } else {
             Ok(Hand { cards })
         }
@@ -158,19 +128,20 @@ struct Game {
 }
 
 impl Game {
-    fn total_winnings(&self) -> usize {
+    fn total_winnings(&self, rules: &Rules) -> Result<usize, String> {
         let mut values = self
             .bids
             .iter()
-            .map(|bid| bid.hand.value())
+            .map(|bid| bid.hand.value(rules))
             .enumerate()
-            .collect::<Vec<(usize, HandValue)>>();
-        values.sort_by_key(|(_, value)| *value);
-        values
+            .map(|(index, value)| Ok((index, value?)))
+            .collect::<Result<Vec<(usize, HandValue)>, String>>()?;
+        values.sort_by(|(_, a), (_, b)| a.cmp(b));
+        Ok(values
             .iter()
             .enumerate()
             .map(|(rank, (index, _))| (rank + 1) * self.bids[*index].bid)
-            .sum()
+            .sum())
     }
 }
 
@@ -186,21 +157,25 @@ impl FromStr for Game {
     }
 }
 
+fn solve(input: &str) {
+    let game = input.parse::<Game>().unwrap();
+    println!(
+        "total winnings: {}",
+        game.total_winnings(&Rules::STANDARD).unwrap()
+    );
+    println!(
+        "total winnings with jokers: {}",
+        game.total_winnings(&Rules::JOKERS).unwrap()
+    );
+}
+
 impl Problem for Day7 {
     fn check(&self) {
-        let total_winnings = include_str!("example.txt")
-            .parse::<Game>()
-            .unwrap()
-            .total_winnings();
-        println!("total winnings: {}", total_winnings);
+        solve(include_str!("example.txt"));
     }
 
     fn solve(&self) {
-        let total_winnings = include_str!("input.txt")
-            .parse::<Game>()
-            .unwrap()
-            .total_winnings();
-        println!("total winnings: {}", total_winnings);
+        solve(include_str!("input.txt"));
     }
 }
 
@@ -210,18 +185,200 @@ mod test_mapper {
 
     #[test]
     fn test_hand_type_ordering() {
-        assert!(HandType::HighCard < HandType::Pair);
-        assert!(HandType::FullHouse == HandType::FullHouse);
+        let high_card = HandType::from_cards(&[1, 2, 3, 4, 5]);
+        let pair = HandType::from_cards(&[1, 2, 3, 1, 5]);
+        let full_house = HandType::from_cards(&[1, 2, 2, 1, 1]);
+        assert!(high_card < pair);
+        assert!(pair < full_house);
+        assert_eq!(full_house, HandType(vec![3, 2]));
     }
 
     #[test]
     fn test_hand_value_ordering() {
-        let hand1 = "AAAQ4".parse::<Hand>().unwrap();
-        let hand2 = "AAAQ3".parse::<Hand>().unwrap();
-        assert!(hand1.value() > hand2.value());
+        let value = |hand: &str| hand.parse::<Hand>().unwrap().value(&Rules::JOKERS).unwrap();
+        assert!(value("AAAQ4") > value("AAAQ3"));
+        assert!(value("2AA2A") < value("AA3A3"));
+    }
 
-        let hand1 = "2AA2A".parse::<Hand>().unwrap();
-        let hand2 = "AA3A3".parse::<Hand>().unwrap();
-        assert!(hand1.value() < hand2.value());
+    #[test]
+    fn test_rules() {
+        let game = include_str!("example.txt").parse::<Game>().unwrap();
+        assert_eq!(game.total_winnings(&Rules::STANDARD), Ok(6440));
+        assert_eq!(game.total_winnings(&Rules::JOKERS), Ok(5905));
+        let hand = "KTJJT".parse::<Hand>().unwrap();
+        assert_eq!(
+            hand.best_hand_type(&Rules::STANDARD),
+            Ok(HandType(vec![2, 2, 1]))
+        );
+        assert_eq!(
+            hand.best_hand_type(&Rules::JOKERS),
+            Ok(HandType(vec![4, 1]))
+        );
+        // A Jack is between a T and a Q
+        let [ten, jack, queen] = ["TT234", "JJ234", "QQ234"]
+            .map(|hand| hand.parse::<Hand>().unwrap().value(&Rules::STANDARD));
+        assert!(ten < jack && jack < queen);
+        let game = "JKKX2 1".parse::<Game>().unwrap();
+        assert_eq!(
+            game.total_winnings(&Rules::STANDARD),
+            Err("invalid card: 'X'".to_string())
+        );
+    }
+
+    #[test]
+    fn test_other_cards() {
+        // Jokers are always J cards
+        let rules = Rules {
+            order: "WQKA",
+            jokers_wild: true,
+        };
+        let hand = "KWKQW".parse::<Hand>().unwrap();
+        assert_eq!(
+            hand.best_hand_type(&rules),
+            Err("invalid card: 'J'".to_string())
+        );
+        let rules = Rules {
+            order: "J23",
+            jokers_wild: false,
+        };
+        assert_eq!(hand.value(&rules), Err("invalid card: 'K'".to_string()));
+        let hand = "J32J".parse::<Hand>().unwrap();
+        assert_eq!(
+            hand.value(&rules),
+            Ok((HandType(vec![2, 1, 1]), vec![0, 2, 1, 0]))
+        );
+    }
+
+    /// Try every assignment of the jokers.
+    fn best_hand_type_brute_force(hand: &Hand) -> HandType {
+        let values = hand.card_values(&Rules::JOKERS).unwrap();
+        let jokers = values.iter().filter(|card| **card == 0).count();
+        (0..jokers)
+            .map(|_| 1..Rules::JOKERS.order.len())
+            .multi_cartesian_product()
+            .map(|joker_cards| {
+                let mut joker_cards = joker_cards.into_iter();
+                let cards = values
+                    .iter()
+                    .map(|card| match card {
+                        0 => joker_cards.next().unwrap(),
+                        card => *card,
+                    })
+                    .collect::<Vec<_>>();
+                HandType::from_cards(&cards)
+            })
+            .max()
+            // No jokers to assign
+            .unwrap_or_else(|| {
+                let cards = values.clone();
+                HandType::from_cards(&cards)
+            })
+    }
+
+    #[test]
+    fn test_jokers() {
+        let hand_type = |hand: &str| {
+            hand.parse::<Hand>()
+                .unwrap()
+                .best_hand_type(&Rules::JOKERS)
+                .unwrap()
+        };
+        assert_eq!(hand_type("JJJJJ"), HandType(vec![5]));
+        assert_eq!(hand_type("KTJJT"), HandType(vec![4, 1]));
+        assert_eq!(hand_type("2J3J4"), HandType(vec![3, 1, 1]));
+        assert_eq!(hand_type("2233J"), HandType(vec![3, 2]));
+        assert_eq!(hand_type("2345J"), HandType(vec![2, 1, 1, 1]));
+    }
+
+    #[test]
+    fn test_jokers_match_brute_force() {
+        let game = include_str!("input.txt").parse::<Game>().unwrap();
+        for bid in &game.bids {
+            assert_eq!(
+                bid.hand.best_hand_type(&Rules::JOKERS),
+                Ok(best_hand_type_brute_force(&bid.hand))
+            );
+        }
+        for hand in ["JJJJJ", "JJJJ2", "J2J3J", "2233J", "AKQJT"] {
+            let hand = hand.parse::<Hand>().unwrap();
+            assert_eq!(
+                hand.best_hand_type(&Rules::JOKERS),
+                Ok(best_hand_type_brute_force(&hand))
+            );
+        }
+    }
+
+    #[test]
+    fn test_any_hand_size() {
+        let hand_type = |hand: &str| {
+            hand.parse::<Hand>()
+                .unwrap()
+                .best_hand_type(&Rules::JOKERS)
+                .unwrap()
+        };
+        assert_eq!(hand_type("AAAKKK"), HandType(vec![3, 3]));
+        assert_eq!(hand_type("AAAJKKK"), HandType(vec![4, 3]));
+        assert_eq!(hand_type("JJJJJJJ"), HandType(vec![7]));
+        assert_eq!(hand_type("A"), HandType(vec![1]));
+        // Six of a kind beats five of a kind and a pair
+        assert!(hand_type("222222") > hand_type("222223"));
+        let game = "AAAKKK 1\nAAKKQQ 10\n2345678 100\nJJJJJJ 1000"
+            .parse::<Game>()
+            .unwrap();
+        assert_eq!(
+            game.total_winnings(&Rules::STANDARD),
+            Ok(100 + 20 + 3 + 4000)
+        );
+        assert_eq!(game.total_winnings(&Rules::JOKERS), Ok(100 + 20 + 3 + 4000));
+        assert!("".parse::<Hand>().is_err());
+    }
+
+    #[test]
+    fn test_large_game() {
+        let mut rng = fastrand::Rng::with_seed(7);
+        let cards = Rules::STANDARD.order.chars().collect::<Vec<_>>();
+        let input = (0..100_000)
+            .map(|bid| {
+                let hand = (0..5)
+                    .map(|_| cards[rng.usize(..cards.len())])
+                    .collect::<String>();
+                format!("{} {}", hand, bid % 1000)
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+        let game = input.parse::<Game>().unwrap();
+        assert!(game.total_winnings(&Rules::JOKERS).unwrap() > 0);
+
+        // Rank a sample of the hands by their brute force values
+        let sample = Game {
+            bids: game
+                .bids
+                .into_iter()
+                .step_by(50)
+                .map(|Bid { hand, bid }| Bid { hand, bid: bid + 1 })
+                .collect(),
+        };
+        let mut reference = sample
+            .bids
+            .iter()
+            .enumerate()
+            .map(|(index, bid)| {
+                let hand_type = best_hand_type_brute_force(&bid.hand);
+                assert_eq!(
+                    bid.hand.best_hand_type(&Rules::JOKERS),
+                    Ok(hand_type.clone())
+                );
+                let values = bid.hand.card_values(&Rules::JOKERS).unwrap();
+                // Equal hands keep their order
+                ((hand_type, values), index, bid.bid)
+            })
+            .collect::<Vec<_>>();
+        reference.sort();
+        let expected = reference
+            .iter()
+            .enumerate()
+            .map(|(rank, (_, _, bid))| (rank + 1) * bid)
+            .sum::<usize>();
+        assert_eq!(sample.total_winnings(&Rules::JOKERS), Ok(expected));
     }
 }
@@ -1,29 +1,44 @@
-use std::{collections::HashMap, str::FromStr, usize};
+use std::{
+    collections::{HashMap, HashSet},
+    str::FromStr,
+};
 
 use aoc_2023_rust_flupke::Problem;
+use itertools::Itertools;
 
-pub struct Day8;
+use crate::common::math::{checked_lcm, chinese_remainder};
 
-#[derive(Clone, Debug, Copy)]
+pub struct Day8 {
+    /// Network file to export
+    input: String,
+    /// File the graph is written to, defaults to stdout
+    output: Option<String>,
+    /// Color the cycle of each ghost in the graph
+    ghost_cycles: bool,
+}
+
+impl Day8 {
+    pub fn new(input: Option<String>, output: Option<String>, ghost_cycles: bool) -> Self {
+        Self {
+            input: input.unwrap_or("src/day_8/input.txt".to_string()),
+            output,
+            ghost_cycles,
+        }
+    }
+}
+
+/// Colors of the ghost cycles in DOT graphs.
+const CYCLE_COLORS: [&str; 6] = ["red", "blue", "darkgreen", "orange", "purple", "brown"];
+
+#[derive(Clone, Debug, Copy, PartialEq, Eq, Hash)]
 enum Direction {
     Left = 0,
     Right = 1,
 }
 
-#[derive(Default, Debug, Clone)]
+#[derive(Debug, Clone)]
 struct Instructions {
     instructions: Vec<Direction>,
-    current_index: usize,
-}
-
-impl Iterator for Instructions {
-    type Item = Direction;
-
-    fn next(&mut self) -> Option<Self::Item> {
-        let return_value = self.instructions.get(self.current_index);
-        self.current_index = (self.current_index + 1) % self.instructions.len();
-        return_value.cloned()
-    }
 }
 
 impl FromStr for Instructions {
@@ -38,57 +53,326 @@ impl FromStr for Instructions {
                 _ => Err(format!("invalid direction: {:?}", char)),
             })
             .collect::<Result<Vec<Direction>, String>>()?;
-        Ok(Instructions {
-            instructions,
-            ..Instructions::default()
+        if instructions.is_empty() {
+            return Err("no instructions".to_string());
+        }
+        Ok(Instructions { instructions })
+    }
+}
+
+/// The nodes of the network, identified by their index.
+#[derive(Debug)]
+struct Network {
+    names: Vec<String>,
+    /// The left and right nodes of each node
+    edges: Vec<[u32; 2]>,
+    /// Whether each node is a start node, ending with A
+    is_start: Vec<bool>,
+    /// Whether each node is an end node, ending with Z
+    is_end: Vec<bool>,
+}
+
+impl Network {
+    fn new(nodes: &[(&str, &str, &str)]) -> Result<Self, String> {
+        let indices = nodes
+            .iter()
+            .enumerate()
+            .map(|(index, (name, _, _))| {
+                u32::try_from(index)
+                    .map(|index| (*name, index))
+                    .map_err(|_| "too many nodes".to_string())
+            })
+            .collect::<Result<HashMap<&str, u32>, String>>()?;
+        if indices.len() != nodes.len() {
+            return Err("duplicate node".to_string());
+        }
+        let index = |name: &str| {
+            indices
+                .get(name)
+                .copied()
+                .ok_or(format!("unknown node: {:?}", name))
+        };
+        let edges = nodes
+            .iter()
+            .map(|(_, left, right)| Ok([index(left)?, index(right)?]))
+            .collect::<Result<Vec<_>, String>>()?;
+        Ok(Self {
+            names: nodes.iter().map(|(name, _, _)| name.to_string()).collect(),
+            edges,
+            is_start: nodes
+                .iter()
+                .map(|(name, _, _)| name.ends_with('A'))
+                .collect(),
+            is_end: nodes
+                .iter()
+                .map(|(name, _, _)| name.ends_with('Z'))
+                .collect(),
         })
     }
+
+    #[cfg(test)]
+    fn index(&self, name: &str) -> Option<u32> {
+        self.names
+            .iter()
+            .position(|node| node == name)
+            .map(|index| index as u32)
+    }
+
+    fn starts(&self) -> impl Iterator<Item = u32> + '_ {
+        (0..self.names.len() as u32).filter(|node| self.is_start[*node as usize])
+    }
+
+    fn next(&self, node: u32, direction: Direction) -> u32 {
+        self.edges[node as usize][direction as usize]
+    }
+}
+
+/// The effect of following all the instructions once, from each node.
+#[derive(Debug)]
+struct JumpTable {
+    /// The node reached at the end of the instructions
+    destination: Vec<u32>,
+    /// The first step of the instructions reaching an end node, if any
+    first_end: Vec<Option<usize>>,
+}
+
+impl JumpTable {
+    fn new(network: &Network, instructions: &Instructions) -> Self {
+        let (destination, first_end) = (0..network.names.len() as u32)
+            .map(|start| {
+                let mut node = start;
+                let mut first_end = None;
+                for (step, direction) in instructions.instructions.iter().enumerate() {
+                    node = network.next(node, *direction);
+                    if first_end.is_none() && network.is_end[node as usize] {
+                        first_end = Some(step + 1);
+                    }
+                }
+                (node, first_end)
+            })
+            .unzip();
+        Self {
+            destination,
+            first_end,
+        }
+    }
+}
+
+/// The path of a ghost, which ends up looping over the same (node, instruction index) states.
+#[derive(Debug, PartialEq)]
+struct GhostCycle {
+    start: String,
+    /// Steps before the cycle at which the ghost is on an end node
+    tail_hits: Vec<usize>,
+    /// Step at which the ghost enters its cycle
+    cycle_start: usize,
+    cycle_length: usize,
+    /// Steps of the first cycle at which the ghost is on an end node
+    cycle_hits: Vec<usize>,
+}
+
+impl GhostCycle {
+    fn is_at_end(&self, step: usize) -> bool {
+        if step < self.cycle_start {
+            self.tail_hits.contains(&step)
+        } else {
+            let step_in_cycle = self.cycle_start + (step - self.cycle_start) % self.cycle_length;
+            self.cycle_hits.contains(&step_in_cycle)
+        }
+    }
+
+    /// Whether the ghost is on an end node exactly at the multiples of the step it first
+    /// reaches one, which is what the LCM shortcut assumes.
+    fn is_aligned(&self) -> bool {
+        let Some(first_hit) = self.tail_hits.iter().chain(&self.cycle_hits).next() else {
+            return false;
+        };
+        // Past the cycle start, hits repeat every cycle length, so this has to be a multiple
+        // of the first hit, and checking the tail and first cycle is enough
+        *first_hit > 0
+            && self.cycle_length.is_multiple_of(*first_hit)
+            && (0..self.cycle_start + self.cycle_length)
+                .all(|step| self.is_at_end(step) == (step > 0 && step % first_hit == 0))
+    }
 }
 
 #[derive(Debug)]
 struct Map {
     instructions: Instructions,
-    nodes: HashMap<String, Vec<String>>,
+    network: Network,
+    jumps: JumpTable,
 }
 
 impl Map {
-    fn count_common_steps_to_end(&self) -> usize {
-        self.nodes
-            .keys()
-            .filter(|key| key.ends_with('A'))
-            .map(|key| self.count_steps_to_end(key))
-            .reduce(least_common_divisor)
-            .unwrap()
+    /// The least common multiple of the steps from each start to an end node, or `None` if it
+    /// overflows or an end is never reached.
+    fn count_common_steps_to_end(&self) -> Option<usize> {
+        self.network
+            .starts()
+            .map(|start| self.count_steps_to_end(start))
+            .reduce(|a, b| checked_lcm(a?, b?))?
     }
 
-    fn count_steps_to_end(&self, start_key: &str) -> usize {
-        let mut steps = 1;
-        let mut current_key = start_key;
-        for direction in self.instructions.clone() {
-            let value = &self.nodes[current_key];
-            current_key = &value[direction as usize];
-            if current_key.ends_with('Z') {
-                break;
+    fn ghost_cycle(&self, start: u32) -> GhostCycle {
+        let instructions = &self.instructions.instructions;
+        // The step at which each (node, instruction index) state was visited
+        let mut visited = vec![None; self.network.names.len() * instructions.len()];
+        let mut hits = Vec::new();
+        let mut node = start;
+        let mut step = 0;
+        let cycle_start = loop {
+            let state = node as usize * instructions.len() + step % instructions.len();
+            if let Some(first_visit) = visited[state] {
+                break first_visit;
             }
-            steps += 1;
+            visited[state] = Some(step);
+            if self.network.is_end[node as usize] {
+                hits.push(step);
+            }
+            node = self
+                .network
+                .next(node, instructions[step % instructions.len()]);
+            step += 1;
+        };
+        let (tail_hits, cycle_hits) = hits.into_iter().partition(|hit| *hit < cycle_start);
+        GhostCycle {
+            start: self.network.names[start as usize].clone(),
+            tail_hits,
+            cycle_start,
+            cycle_length: step - cycle_start,
+            cycle_hits,
         }
-        steps
     }
-}
 
-fn least_common_divisor(a: usize, b: usize) -> usize {
-    a * b / greatest_common_factor(a, b)
-}
+    fn ghost_cycles(&self) -> Vec<GhostCycle> {
+        self.network
+            .starts()
+            .sorted_by_key(|start| &self.network.names[*start as usize])
+            .map(|start| self.ghost_cycle(start))
+            .collect()
+    }
+
+    /// The first step at which all ghosts are on end nodes, from their exact cycles.
+    ///
+    /// Steps before all ghosts are in their cycles are checked one by one. After that, each
+    /// combination of end hits in the cycles gives a system of congruences.
+    fn count_exact_common_steps_to_end(&self) -> Result<usize, String> {
+        let ghosts = self.ghost_cycles();
+        let tail_end = ghosts
+            .iter()
+            .map(|ghost| ghost.cycle_start)
+            .max()
+            .unwrap_or(0);
+        if let Some(step) =
+            (0..tail_end).find(|step| ghosts.iter().all(|ghost| ghost.is_at_end(*step)))
+        {
+            return Ok(step);
+        }
+        ghosts
+            .iter()
+            .map(|ghost| {
+                ghost
+                    .cycle_hits
+                    .iter()
+                    .map(|hit| (*hit as i64, ghost.cycle_length as i64))
+            })
+            .multi_cartesian_product()
+            .filter_map(|congruences| chinese_remainder(&congruences))
+            .map(|(step, period)| {
+                // The first solution once all ghosts are in their cycles
+                let (step, period) = (step as usize, period as usize);
+                step + tail_end.saturating_sub(step).div_ceil(period) * period
+            })
+            .min()
+            .ok_or("ghosts are never all on end nodes at the same step".to_string())
+    }
+
+    /// The edges, as a node and the direction taken from it, followed in the cycle of the
+    /// ghost leaving `start`.
+    fn cycle_edges(&self, start: u32) -> HashSet<(u32, Direction)> {
+        let instructions = &self.instructions.instructions;
+        let ghost = self.ghost_cycle(start);
+        let mut edges = HashSet::new();
+        let mut node = start;
+        for step in 0..ghost.cycle_start + ghost.cycle_length {
+            let direction = instructions[step % instructions.len()];
+            if step >= ghost.cycle_start {
+                edges.insert((node, direction));
+            }
+            node = self.network.next(node, direction);
+        }
+        edges
+    }
 
-fn greatest_common_factor(a: usize, b: usize) -> usize {
-    let mut a = a;
-    let mut b = b;
-    while b != 0 {
-        let temp = b;
-        b = a % b;
-        a = temp;
+    /// The network as a Graphviz DOT graph, with start nodes in green and end nodes in red.
+    /// With `ghost_cycles`, the edges of the cycle of each ghost are colored.
+    fn to_dot(&self, ghost_cycles: bool) -> String {
+        let network = &self.network;
+        let mut edge_colors = HashMap::new();
+        if ghost_cycles {
+            let starts = network
+                .starts()
+                .sorted_by_key(|start| &network.names[*start as usize]);
+            for (start, color) in starts.zip(CYCLE_COLORS.iter().cycle()) {
+                for edge in self.cycle_edges(start) {
+                    edge_colors.entry(edge).or_insert(*color);
+                }
+            }
+        }
+        let mut lines = vec!["digraph network {".to_string()];
+        for (node, name) in network.names.iter().enumerate() {
+            if network.is_start[node] {
+                lines.push(format!("  {:?} [style=filled, fillcolor=palegreen];", name));
+            } else if network.is_end[node] {
+                lines.push(format!(
+                    "  {:?} [style=filled, fillcolor=lightcoral];",
+                    name
+                ));
+            }
+        }
+        for (node, name) in network.names.iter().enumerate() {
+            let [left, right] = network.edges[node];
+            let directions: &[(Direction, &str)] = if left == right {
+                &[(Direction::Left, "LR")]
+            } else {
+                &[(Direction::Left, "L"), (Direction::Right, "R")]
+            };
+            for (direction, label) in directions {
+                let target = &network.names[network.next(node as u32, *direction) as usize];
+                let mut attributes = format!("label={:?}", label);
+                // A shared edge belongs to a cycle if either direction does
+                let color = edge_colors.get(&(node as u32, *direction)).or_else(|| {
+                    (left == right)
+                        .then(|| edge_colors.get(&(node as u32, Direction::Right)))
+                        .flatten()
+                });
+                if let Some(color) = color {
+                    attributes.push_str(&format!(", color={}, penwidth=2", color));
+                }
+                lines.push(format!("  {:?} -> {:?} [{}];", name, target, attributes));
+            }
+        }
+        lines.push("}".to_string());
+        lines.join("\n")
+    }
+
+    /// The number of steps to reach an end node from `start`, or `None` if it never happens.
+    ///
+    /// Whole runs of the instructions that don't reach an end node are skipped at once.
+    fn count_steps_to_end(&self, start: u32) -> Option<usize> {
+        let mut steps = 0;
+        let mut node = start as usize;
+        // After as many runs as there are nodes, a node was visited twice at the same
+        // instruction index, so the walk is looping
+        for _ in 0..self.network.names.len() {
+            if let Some(first_end) = self.jumps.first_end[node] {
+                return Some(steps + first_end);
+            }
+            steps += self.instructions.instructions.len();
+            node = self.jumps.destination[node] as usize;
+        }
+        None
     }
-    a
 }
 
 impl FromStr for Map {
@@ -97,43 +381,193 @@ impl FromStr for Map {
     fn from_str(input: &str) -> Result<Self, Self::Err> {
         let mut lines = input.lines();
 
-        let instructions = lines.next().unwrap().parse::<Instructions>()?;
+        let instructions = lines.next().unwrap_or("").parse::<Instructions>()?;
 
-        let mut nodes = HashMap::new();
+        let mut nodes = Vec::new();
         lines.next();
         for line in lines {
             let (key, value) = line
                 .split_once(" = ")
                 .ok_or("invalid map line: '=' not found")?;
-            let (mut left, mut right) = value
-                .split_once(", ")
-                .ok_or("invalid map line: ',' not found")?;
-            left = &left[1..];
-            right = &right[..right.len() - 1];
-            nodes.insert(key.to_string(), vec![left.to_string(), right.to_string()]);
+            let (left, right) = value
+                .strip_prefix('(')
+                .and_then(|value| value.strip_suffix(')'))
+                .and_then(|value| value.split_once(", "))
+                .ok_or(format!("invalid map line: {:?}", line))?;
+            nodes.push((key, left, right));
         }
+        let network = Network::new(&nodes)?;
 
         Ok(Map {
+            jumps: JumpTable::new(&network, &instructions),
             instructions,
-            nodes,
+            network,
         })
     }
 }
 
+fn solve(input: &str) {
+    let map = input.parse::<Map>().unwrap();
+    println!("steps: {}", map.count_exact_common_steps_to_end().unwrap());
+    let misaligned = map
+        .ghost_cycles()
+        .into_iter()
+        .filter(|ghost| !ghost.is_aligned())
+        .collect::<Vec<_>>();
+    if !misaligned.is_empty() {
+        println!(
+            "LCM shortcut invalid, it would give {:?} steps",
+            map.count_common_steps_to_end()
+        );
+        for ghost in misaligned {
+            println!(
+                "  ghost from {}: end hits {:?} then {:?} every {} steps",
+                ghost.start, ghost.tail_hits, ghost.cycle_hits, ghost.cycle_length
+            );
+        }
+    }
+}
+
 impl Problem for Day8 {
     fn check(&self) {
-        let steps = include_str!("example.txt")
-            .parse::<Map>()
-            .unwrap()
-            .count_common_steps_to_end();
-        println!("steps: {}", steps);
+        solve(include_str!("example.txt"));
     }
 
     fn solve(&self) {
-        let steps = include_str!("input.txt")
-            .parse::<Map>()
+        solve(include_str!("input.txt"));
+    }
+
+    fn graph(&self) {
+        let map = std::fs::read_to_string(&self.input)
             .unwrap()
-            .count_common_steps_to_end();
-        println!("steps: {}", steps);
+            .parse::<Map>()
+            .unwrap();
+        let dot = map.to_dot(self.ghost_cycles);
+        match &self.output {
+            Some(path) => std::fs::write(path, dot + "\n").unwrap(),
+            None => println!("{}", dot),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The ghost from 11A reaches 11Z after 1 step, then every 3 steps, so the LCM shortcut
+    /// gives 2 instead of 4.
+    const MISALIGNED: &str = "L
+
+11A = (11Z, 11Z)
+11Z = (11B, 11B)
+11B = (11C, 11C)
+11C = (11Z, 11Z)
+22A = (22B, 22B)
+22B = (22Z, 22Z)
+22Z = (22B, 22B)";
+
+    #[test]
+    fn test_example() {
+        let map = include_str!("example.txt").parse::<Map>().unwrap();
+        assert_eq!(map.count_common_steps_to_end(), Some(6));
+        assert_eq!(map.count_exact_common_steps_to_end(), Ok(6));
+        assert!(map.ghost_cycles().iter().all(GhostCycle::is_aligned));
+    }
+
+    #[test]
+    fn test_ghost_cycle() {
+        let map = include_str!("example.txt").parse::<Map>().unwrap();
+        assert_eq!(
+            map.ghost_cycle(map.network.index("22A").unwrap()),
+            GhostCycle {
+                start: "22A".to_string(),
+                tail_hits: vec![],
+                cycle_start: 1,
+                cycle_length: 6,
+                cycle_hits: vec![3, 6],
+            }
+        );
+    }
+
+    #[test]
+    fn test_network() {
+        let map = include_str!("example.txt").parse::<Map>().unwrap();
+        let [start, b, z] = ["11A", "11B", "11Z"].map(|name| map.network.index(name).unwrap());
+        assert_eq!(map.network.edges[b as usize][1], z);
+        assert_eq!(map.network.starts().count(), 2);
+        assert!(map.network.is_start[start as usize] && map.network.is_end[z as usize]);
+        // LR from 11A goes to 11B then 11Z
+        assert_eq!(map.jumps.destination[start as usize], z);
+        assert_eq!(map.jumps.first_end[start as usize], Some(2));
+        assert_eq!(map.count_steps_to_end(start), Some(2));
+        assert!("LR\n\nAAA = (BBB, BBB)".parse::<Map>().is_err());
+        assert!("LX\n\nAAA = (AAA, AAA)".parse::<Map>().is_err());
+    }
+
+    #[test]
+    fn test_jumps() {
+        // The end is only reached on the 4th run of the instructions
+        let map = "LLR\n\nAAA = (BBB, BBB)\nBBB = (CCC, CCC)\nCCC = (DDD, DDD)\n\
+                   DDD = (EEE, EEE)\nEEE = (FFF, FFF)\nFFF = (GGG, GGG)\n\
+                   GGG = (HHH, HHH)\nHHH = (IIZ, IIZ)\nIIZ = (IIZ, IIZ)\nXXA = (XXA, XXA)"
+            .parse::<Map>()
+            .unwrap();
+        let start = map.network.index("AAA").unwrap();
+        assert_eq!(map.count_steps_to_end(start), Some(8));
+        assert_eq!(
+            map.count_steps_to_end(map.network.index("XXA").unwrap()),
+            None
+        );
+        assert_eq!(map.count_common_steps_to_end(), None);
+    }
+
+    #[test]
+    fn test_to_dot() {
+        let map = "LR\n\n11A = (11B, XXX)\n11B = (XXX, 11Z)\n11Z = (11B, XXX)\nXXX = (XXX, XXX)"
+            .parse::<Map>()
+            .unwrap();
+        assert_eq!(
+            map.to_dot(false),
+            "digraph network {
+  \"11A\" [style=filled, fillcolor=palegreen];
+  \"11Z\" [style=filled, fillcolor=lightcoral];
+  \"11A\" -> \"11B\" [label=\"L\"];
+  \"11A\" -> \"XXX\" [label=\"R\"];
+  \"11B\" -> \"XXX\" [label=\"L\"];
+  \"11B\" -> \"11Z\" [label=\"R\"];
+  \"11Z\" -> \"11B\" [label=\"L\"];
+  \"11Z\" -> \"XXX\" [label=\"R\"];
+  \"XXX\" -> \"XXX\" [label=\"LR\"];
+}"
+        );
+        let dot = map.to_dot(true);
+        assert!(dot.contains("\"11B\" -> \"11Z\" [label=\"R\", color=red, penwidth=2];"));
+        assert!(dot.contains("\"11Z\" -> \"11B\" [label=\"L\", color=red, penwidth=2];"));
+        assert!(dot.contains("\"11A\" -> \"11B\" [label=\"L\"];"));
+    }
+
+    #[test]
+    fn test_misaligned_cycles() {
+        let map = MISALIGNED.parse::<Map>().unwrap();
+        let ghosts = map.ghost_cycles();
+        assert!(!ghosts[0].is_aligned());
+        assert!(ghosts[1].is_aligned());
+        assert_eq!(map.count_common_steps_to_end(), Some(2));
+        assert_eq!(map.count_exact_common_steps_to_end(), Ok(4));
+        // Brute force
+        assert_eq!(
+            (0..).find(|step| ghosts.iter().all(|ghost| ghost.is_at_end(*step))),
+            Some(4)
+        );
+    }
+
+    #[test]
+    fn test_never_synchronized() {
+        // The first ghost is on 11Z at odd steps, the second one on 22Z at even steps
+        let map = "L\n\n11A = (11Z, 11Z)\n11Z = (11A, 11A)\n22A = (22B, 22B)\n22B = (22Z, 22Z)\n\
+                   22Z = (22B, 22B)"
+            .parse::<Map>()
+            .unwrap();
+        assert!(map.count_exact_common_steps_to_end().is_err());
     }
 }
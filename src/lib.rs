@@ -1,8 +1,55 @@
-use std::str::FromStr;
+use std::{
+    fmt::Display,
+    str::FromStr,
+    sync::atomic::{AtomicU8, Ordering},
+};
 
 pub trait Problem {
     fn check(&self);
     fn solve(&self);
+
+    fn analyze(&self) {
+        panic!("Analysis not implemented");
+    }
+
+    fn graph(&self) {
+        panic!("Graph export not implemented");
+    }
+}
+
+static VERBOSITY: AtomicU8 = AtomicU8::new(0);
+
+/// Set the verbosity of the diagnostics emitted with [`trace!`]. Nothing is traced at 0, the
+/// default.
+pub fn set_verbosity(verbosity: u8) {
+    VERBOSITY.store(verbosity, Ordering::Relaxed);
+}
+
+pub fn verbosity() -> u8 {
+    VERBOSITY.load(Ordering::Relaxed)
+}
+
+/// Format a trace line: the scope it comes from, followed by `key=value` fields.
+pub fn format_trace(scope: &str, fields: &[(&str, String)]) -> String {
+    let fields = fields
+        .iter()
+        .map(|(key, value)| format!("{}={}", key, value))
+        .collect::<Vec<_>>();
+    format!("[{}] {}", scope, fields.join(" "))
+}
+
+/// Print a diagnostic line on stderr when tracing is enabled, e.g.
+/// `trace!("day 5", stage = "soil", ranges = 3)` prints `[day 5] stage=soil ranges=3`.
+#[macro_export]
+macro_rules! trace {
+    ($scope:expr, $($key:ident = $value:expr),+ $(,)?) => {
+        if $crate::verbosity() > 0 {
+            eprintln!(
+                "{}",
+                $crate::format_trace($scope, &[$((stringify!($key), $value.to_string())),+])
+            );
+        }
+    };
 }
 
 pub fn split_numbers<T: FromStr>(line: &str, separator: char) -> Vec<T> {
@@ -10,3 +57,175 @@ pub fn split_numbers<T: FromStr>(line: &str, separator: char) -> Vec<T> {
         .filter_map(|n| n.trim().parse::<T>().ok())
         .collect::<Vec<T>>()
 }
+
+/// An error raised by the parsing helpers, keeping the offending token.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    pub token: String,
+    pub message: String,
+}
+
+impl ParseError {
+    pub fn new(token: &str, message: &str) -> Self {
+        Self {
+            token: token.to_string(),
+            message: message.to_string(),
+        }
+    }
+}
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {:?}", self.message, self.token)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+impl From<ParseError> for String {
+    fn from(error: ParseError) -> Self {
+        error.to_string()
+    }
+}
+
+/// Like [`split_numbers`], but fails on the first token that can't be parsed instead of
+/// dropping it. Empty tokens (e.g. from repeated separators) are still skipped.
+pub fn try_split_numbers<T: FromStr>(line: &str, separator: char) -> Result<Vec<T>, ParseError> {
+    line.split(separator)
+        .map(str::trim)
+        .filter(|token| !token.is_empty())
+        .map(|token| {
+            token
+                .parse::<T>()
+                .map_err(|_| ParseError::new(token, "invalid number"))
+        })
+        .collect()
+}
+
+/// Extract all the integers found in `line`, ignoring anything else. A `-` directly before
+/// digits makes the number negative.
+pub fn extract_integers<T: FromStr>(line: &str) -> Result<Vec<T>, ParseError> {
+    let bytes = line.as_bytes();
+    let mut numbers = Vec::new();
+    let mut index = 0;
+    while index < bytes.len() {
+        let start = index;
+        if bytes[index] == b'-' && bytes.get(index + 1).is_some_and(u8::is_ascii_digit) {
+            index += 1;
+        }
+        if bytes[index].is_ascii_digit() {
+            while index < bytes.len() && bytes[index].is_ascii_digit() {
+                index += 1;
+            }
+            let token = &line[start..index];
+            numbers.push(
+                token
+                    .parse::<T>()
+                    .map_err(|_| ParseError::new(token, "invalid number"))?,
+            );
+        } else {
+            index += 1;
+        }
+    }
+    Ok(numbers)
+}
+
+/// Split `input` into blocks separated by blank lines.
+pub fn split_blocks(input: &str) -> Vec<String> {
+    let mut blocks = Vec::new();
+    let mut current: Vec<&str> = Vec::new();
+    for line in input.lines() {
+        if line.trim().is_empty() {
+            if !current.is_empty() {
+                blocks.push(current.join("\n"));
+                current.clear();
+            }
+        } else {
+            current.push(line);
+        }
+    }
+    if !current.is_empty() {
+        blocks.push(current.join("\n"));
+    }
+    blocks
+}
+
+/// Split a `key: values` line, returning the trimmed key and values.
+pub fn parse_header(line: &str) -> Result<(&str, &str), ParseError> {
+    line.split_once(':')
+        .map(|(key, values)| (key.trim(), values.trim()))
+        .ok_or_else(|| ParseError::new(line, "missing ':' separator"))
+}
+
+/// Parse a `key: values` line where values are numbers separated by `separator`.
+pub fn parse_header_numbers<T: FromStr>(
+    line: &str,
+    separator: char,
+) -> Result<(&str, Vec<T>), ParseError> {
+    let (key, values) = parse_header(line)?;
+    Ok((key, try_split_numbers(values, separator)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_trace() {
+        assert_eq!(
+            format_trace(
+                "day 5",
+                &[("stage", "soil".to_string()), ("ranges", 3.to_string())]
+            ),
+            "[day 5] stage=soil ranges=3"
+        );
+        assert_eq!(verbosity(), 0);
+    }
+
+    #[test]
+    fn test_try_split_numbers() {
+        assert_eq!(try_split_numbers::<u32>(" 1  2 3 ", ' '), Ok(vec![1, 2, 3]));
+        assert_eq!(
+            try_split_numbers::<u32>("1 x 3", ' '),
+            Err(ParseError::new("x", "invalid number"))
+        );
+    }
+
+    #[test]
+    fn test_extract_integers() {
+        assert_eq!(
+            extract_integers::<i64>("x=-12, y=5 -> 3-4"),
+            Ok(vec![-12, 5, 3, -4])
+        );
+        assert_eq!(extract_integers::<i64>("no numbers - here"), Ok(vec![]));
+        assert_eq!(
+            extract_integers::<u8>("1 300"),
+            Err(ParseError::new("300", "invalid number"))
+        );
+    }
+
+    #[test]
+    fn test_split_blocks() {
+        assert_eq!(
+            split_blocks("a\nb\n\n\nc\n  \nd\n"),
+            vec!["a\nb".to_string(), "c".to_string(), "d".to_string()]
+        );
+        assert!(split_blocks("\n\n").is_empty());
+    }
+
+    #[test]
+    fn test_parse_header() {
+        assert_eq!(parse_header("Card 1: 41 48"), Ok(("Card 1", "41 48")));
+        assert!(parse_header("Card 1").is_err());
+        assert_eq!(
+            parse_header_numbers::<u64>("Time:      7  15   30", ' '),
+            Ok(("Time", vec![7, 15, 30]))
+        );
+        assert_eq!(
+            parse_header_numbers::<u64>("Time: 7 a", ' ')
+                .unwrap_err()
+                .to_string(),
+            "invalid number: \"a\""
+        );
+    }
+}
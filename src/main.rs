@@ -25,25 +25,66 @@ struct Cli {
 
     #[arg()]
     day: u8,
+
+    /// Number words for day 1: a language (english, french, german or spanish, optionally
+    /// suffixed with +zero), or the path of a file with word=digit lines
+    #[arg(long)]
+    vocabulary: Option<String>,
+
+    /// Input file for the analyze and graph commands, instead of the day's puzzle input
+    #[arg(long)]
+    input: Option<String>,
+
+    /// Bag to check games against for day 2, e.g. "12 red, 13 green, 14 blue"
+    #[arg(long)]
+    bag: Option<String>,
+
+    /// Gear rule for day 3, as `symbols:parts count:combination`, where the parts count is a
+    /// number or a min-max range, and the combination product, sum or max, e.g. "*:2:product"
+    #[arg(long)]
+    gear_rule: Option<String>,
+
+    /// Print the analysis as JSON instead of a table (day 4)
+    #[arg(long)]
+    json: bool,
+
+    /// File to write the graph to, instead of stdout (day 8)
+    #[arg(long)]
+    output: Option<String>,
+
+    /// Color the cycle of each ghost in the graph (day 8)
+    #[arg(long)]
+    ghost_cycles: bool,
+
+    /// Print stage-by-stage diagnostics on stderr
+    #[arg(short = 'v', long = "trace", action = clap::ArgAction::Count)]
+    trace: u8,
 }
 
 #[derive(ValueEnum, Clone, Debug)]
 enum Command {
     Check,
     Solve,
+    Analyze,
+    Graph,
 }
 
 fn main() {
     let args = Cli::parse();
+    aoc_2023_rust_flupke::set_verbosity(args.trace);
     let module: Box<dyn Problem> = match args.day {
-        1 => Box::new(day_1::Day1),
-        2 => Box::new(day_2::Day2),
-        3 => Box::new(day_3::Day3),
-        4 => Box::new(day_4::Day4),
-        5 => Box::new(day_5::Day5),
+        1 => Box::new(day_1::Day1::new(args.vocabulary.as_deref()).unwrap()),
+        2 => Box::new(day_2::Day2::new(args.input.clone(), args.bag.as_deref()).unwrap()),
+        3 => Box::new(day_3::Day3::new(args.gear_rule.as_deref()).unwrap()),
+        4 => Box::new(day_4::Day4::new(args.input.clone(), args.json)),
+        5 => Box::new(day_5::Day5::new(args.input.clone())),
         6 => Box::new(day_6::Day6),
         7 => Box::new(day_7::Day7),
-        8 => Box::new(day_8::Day8),
+        8 => Box::new(day_8::Day8::new(
+            args.input.clone(),
+            args.output.clone(),
+            args.ghost_cycles,
+        )),
         9 => Box::new(day_9::Day9),
         10 => Box::new(day_10::Day10),
         11 => Box::new(day_11::Day11),
@@ -57,5 +98,7 @@ fn main() {
     match args.command {
         Command::Solve => module.solve(),
         Command::Check => module.check(),
+        Command::Analyze => module.analyze(),
+        Command::Graph => module.graph(),
     }
 }